@@ -0,0 +1,128 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use ethers::{
+    providers::Middleware,
+    types::{H160, U256},
+    utils::format_units,
+};
+use prometheus::{Gauge, IntGauge};
+use tokio::time::sleep;
+use utils::metrics;
+
+use crate::ChainState;
+
+const BALANCE_POLL_INTERVAL: Duration = Duration::from_secs(60);
+// rough upper bounds of the gas used by each transaction
+const EPOCH_REGISTRATION_GAS: u64 = 150_000;
+const SAMPLE_SUBMISSION_GAS: u64 = 300_000;
+
+#[derive(Debug, Clone, Default)]
+pub struct BalanceMonitorConfig {
+    // none if data availability sampling is disabled
+    pub miner_address: Option<H160>,
+    pub low_balance_threshold: Option<U256>,
+}
+
+struct MonitoredAccount {
+    name: &'static str,
+    address: H160,
+    gas_per_tx: u64,
+    balance_gauge: &'static Gauge,
+    remaining_gauge: &'static IntGauge,
+}
+
+pub fn start_balance_monitor(chain_state: Arc<ChainState>, config: BalanceMonitorConfig) {
+    let mut accounts = vec![MonitoredAccount {
+        name: "signer",
+        address: chain_state.signer_address,
+        gas_per_tx: EPOCH_REGISTRATION_GAS,
+        balance_gauge: &metrics::SIGNER_BALANCE,
+        remaining_gauge: &metrics::SIGNER_REGISTRATIONS_REMAINING,
+    }];
+    if let Some(address) = config.miner_address {
+        accounts.push(MonitoredAccount {
+            name: "miner",
+            address,
+            gas_per_tx: SAMPLE_SUBMISSION_GAS,
+            balance_gauge: &metrics::MINER_BALANCE,
+            remaining_gauge: &metrics::MINER_SUBMISSIONS_REMAINING,
+        });
+    }
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) =
+                check_balances(&chain_state, &accounts, config.low_balance_threshold).await
+            {
+                error!("check account balances error: {:?}", e);
+            }
+            sleep(BALANCE_POLL_INTERVAL).await;
+        }
+    });
+}
+
+// transactions of `gas_per_tx` the balance pays for at the gas price
+fn remaining_transactions(balance: U256, gas_price: U256, gas_per_tx: u64) -> i64 {
+    let tx_cost = gas_price * gas_per_tx;
+    if tx_cost.is_zero() {
+        i64::MAX
+    } else {
+        (balance / tx_cost).min(U256::from(i64::MAX)).as_u64() as i64
+    }
+}
+
+async fn check_balances(
+    chain_state: &ChainState,
+    accounts: &[MonitoredAccount],
+    low_balance_threshold: Option<U256>,
+) -> Result<()> {
+    let gas_price = chain_state.provider.get_gas_price().await?;
+    for account in accounts {
+        let balance = chain_state
+            .provider
+            .get_balance(account.address, None)
+            .await?;
+        let balance_in_ether: f64 = format_units(balance, "ether")?.parse()?;
+        account.balance_gauge.set(balance_in_ether);
+
+        let remaining = remaining_transactions(balance, gas_price, account.gas_per_tx);
+        account.remaining_gauge.set(remaining);
+
+        if matches!(low_balance_threshold, Some(x) if balance < x) {
+            warn!(
+                "{} account {:?} balance is low: {:?} ether, enough for about {:?} more transactions",
+                account.name, account.address, balance_in_ether, remaining
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_transactions_test() {
+        let gwei = U256::exp10(9);
+        // 150_000 gas at 1 gwei costs 0.00015 ether
+        let balance = gwei * 150_000 * 10 + 1;
+        assert_eq!(
+            remaining_transactions(balance, gwei, EPOCH_REGISTRATION_GAS),
+            10
+        );
+        assert_eq!(
+            remaining_transactions(balance, gwei, SAMPLE_SUBMISSION_GAS),
+            5
+        );
+        assert_eq!(
+            remaining_transactions(U256::zero(), gwei, SAMPLE_SUBMISSION_GAS),
+            0
+        );
+        assert_eq!(
+            remaining_transactions(balance, U256::zero(), SAMPLE_SUBMISSION_GAS),
+            i64::MAX
+        );
+        assert_eq!(remaining_transactions(U256::MAX, U256::one(), 1), i64::MAX);
+    }
+}
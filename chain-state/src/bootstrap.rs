@@ -0,0 +1,379 @@
+use std::{
+    cmp,
+    collections::{BTreeSet, HashMap},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Result};
+use contract_interface::{
+    da_entrance::{DataUploadFilter, ErasureCommitmentVerifiedFilter},
+    DAEntrance,
+};
+use ethers::{
+    abi::RawLog,
+    prelude::EthLogDecode,
+    providers::Middleware,
+    types::{BlockNumber, Filter, Log, U256},
+};
+use storage::{blob_status_db::BlobStatus, misc_db::MiscDB, MetadataExport};
+
+use crate::{da_handler::MAX_LOGS_PAGINATION, ChainState};
+
+// blobs and epochs checked against the chain, spread evenly over the export
+const SPOT_CHECKS: usize = 32;
+// blocks before the export block whose DA events must all be reflected in the export
+const RECENT_BLOCKS_CHECKED: u64 = 1000;
+
+// evenly spaced items, at most `SPOT_CHECKS` of them
+fn spot_check_sample<T>(items: &[T]) -> impl Iterator<Item = &T> {
+    items
+        .iter()
+        .step_by(cmp::max(items.len() / SPOT_CHECKS, 1))
+        .take(SPOT_CHECKS)
+}
+
+impl ChainState {
+    /// Imports metadata exported by another node, after spot checks against the chain, into a
+    /// node that has not synced any DA events yet. DA events are synced on from the export block.
+    pub async fn bootstrap_metadata(&self, path: impl AsRef<Path>) -> Result<()> {
+        if self.db.get_sync_progress().await?.is_some() {
+            info!("DA events synced already, skipping the metadata bootstrap");
+            return Ok(());
+        }
+        let export = MetadataExport::read(path)?;
+        check_blobs(&self.da_entrance, &export).await?;
+        let quorums = self.check_quorums(&export).await?;
+        if !quorums {
+            warn!("quorums of the metadata are not verified as this signer's, fetching them again");
+        }
+        self.db.import_metadata(&export, quorums).await?;
+        info!(
+            "metadata of {:?} blobs bootstrapped, syncing DA events from block {:?}",
+            export.blobs.len(),
+            export.block_number
+        );
+        Ok(())
+    }
+
+    // whether the quorums were assigned to this signer, which only shows if it was assigned any
+    // slices in the checked epochs
+    async fn check_quorums(&self, export: &MetadataExport) -> Result<bool> {
+        let mut assigned_any = false;
+        for (epoch, assigned) in spot_check_sample(&export.quorums) {
+            let quorum_cnt = self
+                .da_signers
+                .quorum_count(U256::from(*epoch))
+                .call()
+                .await?
+                .as_u64();
+            if quorum_cnt != assigned.len() as u64 {
+                bail!(
+                    "{} quorums of epoch {} in the metadata, {} on chain",
+                    assigned.len(),
+                    epoch,
+                    quorum_cnt
+                );
+            }
+            if self.get_assigned_slices(*epoch, quorum_cnt).await? != *assigned {
+                return Ok(false);
+            }
+            assigned_any |= assigned.iter().any(|slices| !slices.0.is_empty());
+        }
+        Ok(assigned_any)
+    }
+}
+
+/// Spot-checks the blobs of an export against the DA events and erasure commitments on chain.
+async fn check_blobs<M: Middleware + 'static>(
+    da_entrance: &DAEntrance<M>,
+    export: &MetadataExport,
+) -> Result<()> {
+    let finalized = da_entrance
+        .client_ref()
+        .get_block(BlockNumber::Finalized)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))?
+        .and_then(|b| b.number)
+        .ok_or_else(|| anyhow!("finalized block number missing"))?
+        .as_u64();
+    if export.block_number > finalized + 1 {
+        bail!(
+            "metadata exported at block {} beyond the finalized block {}",
+            export.block_number,
+            finalized
+        );
+    }
+    check_recent_events(da_entrance, export).await?;
+    check_verified_blobs(da_entrance, export).await?;
+    check_uploaded_blobs(da_entrance, export).await
+}
+
+async fn get_logs<M: Middleware>(da_entrance: &DAEntrance<M>, filter: &Filter) -> Result<Vec<Log>> {
+    da_entrance
+        .client_ref()
+        .get_logs(filter)
+        .await
+        .map_err(|e| anyhow!(e.to_string()))
+}
+
+async fn check_recent_events<M: Middleware + 'static>(
+    da_entrance: &DAEntrance<M>,
+    export: &MetadataExport,
+) -> Result<()> {
+    let blobs: HashMap<_, _> = export
+        .blobs
+        .iter()
+        .map(|b| ((b.epoch, b.quorum_id, b.storage_root), b.status))
+        .collect();
+    let mut l = export.block_number.saturating_sub(RECENT_BLOCKS_CHECKED);
+    while l < export.block_number {
+        let r = cmp::min(l + MAX_LOGS_PAGINATION, export.block_number - 1);
+        let filter = da_entrance
+            .data_upload_filter()
+            .from_block(l)
+            .to_block(r)
+            .address(da_entrance.address().into())
+            .filter;
+        for log in get_logs(da_entrance, &filter).await? {
+            let event = DataUploadFilter::decode_log(&RawLog {
+                topics: log.topics,
+                data: log.data.to_vec(),
+            })?;
+            let key = (
+                event.epoch.as_u64(),
+                event.quorum_id.as_u64(),
+                event.data_root,
+            );
+            if !blobs.contains_key(&key) {
+                bail!(
+                    "blob uploaded at block {:?} missing in the metadata",
+                    log.block_number
+                );
+            }
+        }
+        let filter = da_entrance
+            .erasure_commitment_verified_filter()
+            .from_block(l)
+            .to_block(r)
+            .address(da_entrance.address().into())
+            .filter;
+        for log in get_logs(da_entrance, &filter).await? {
+            let event = ErasureCommitmentVerifiedFilter::decode_log(&RawLog {
+                topics: log.topics,
+                data: log.data.to_vec(),
+            })?;
+            let key = (
+                event.epoch.as_u64(),
+                event.quorum_id.as_u64(),
+                event.data_root,
+            );
+            if !matches!(blobs.get(&key), Some(BlobStatus::VERIFIED)) {
+                bail!(
+                    "blob verified at block {:?} not verified in the metadata",
+                    log.block_number
+                );
+            }
+        }
+        l = r + 1;
+    }
+    Ok(())
+}
+
+async fn check_verified_blobs<M: Middleware + 'static>(
+    da_entrance: &DAEntrance<M>,
+    export: &MetadataExport,
+) -> Result<()> {
+    let verified: Vec<_> = export
+        .blobs
+        .iter()
+        .filter(|b| b.status == BlobStatus::VERIFIED)
+        .collect();
+    for blob in spot_check_sample(&verified) {
+        let commitment = da_entrance
+            .verified_erasure_commitment(
+                blob.storage_root,
+                U256::from(blob.epoch),
+                U256::from(blob.quorum_id),
+            )
+            .call()
+            .await?;
+        if commitment.x.is_zero() && commitment.y.is_zero() {
+            bail!(
+                "blob {:?} of epoch {:?} quorum {:?} is not verified on chain",
+                hex::encode(blob.storage_root),
+                blob.epoch,
+                blob.quorum_id
+            );
+        }
+    }
+    Ok(())
+}
+
+// uploads are searched back from the export block, until they are of epochs before the sampled
+// blobs since the epochs of uploads only grow
+async fn check_uploaded_blobs<M: Middleware + 'static>(
+    da_entrance: &DAEntrance<M>,
+    export: &MetadataExport,
+) -> Result<()> {
+    let uploaded: Vec<_> = export
+        .blobs
+        .iter()
+        .filter(|b| b.status == BlobStatus::UPLOADED)
+        .collect();
+    let mut missing: BTreeSet<_> = spot_check_sample(&uploaded)
+        .map(|b| (b.epoch, b.quorum_id, b.storage_root))
+        .collect();
+    let mut to = export.block_number.checked_sub(1);
+    while let (Some(r), Some(&(min_epoch, _, _))) = (to, missing.first()) {
+        let l = r.saturating_sub(MAX_LOGS_PAGINATION);
+        let filter = da_entrance
+            .data_upload_filter()
+            .from_block(l)
+            .to_block(r)
+            .address(da_entrance.address().into())
+            .filter;
+        let mut passed_sampled = false;
+        for log in get_logs(da_entrance, &filter).await? {
+            let event = DataUploadFilter::decode_log(&RawLog {
+                topics: log.topics,
+                data: log.data.to_vec(),
+            })?;
+            missing.remove(&(
+                event.epoch.as_u64(),
+                event.quorum_id.as_u64(),
+                event.data_root,
+            ));
+            passed_sampled |= event.epoch.as_u64() < min_epoch;
+        }
+        if passed_sampled {
+            break;
+        }
+        to = l.checked_sub(1);
+    }
+    if let Some((epoch, quorum_id, storage_root)) = missing.first() {
+        bail!(
+            "uploaded blob {:?} of epoch {:?} quorum {:?} has no DataUpload event on chain",
+            hex::encode(storage_root),
+            epoch,
+            quorum_id
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{self, Token},
+        contract::EthEvent,
+        providers::{MockProvider, Provider},
+        types::{Block, Bytes, H160, H256},
+    };
+    use std::sync::Arc;
+    use storage::{blob_status_db::BlobStatusDB, Storage};
+
+    const VERIFIED_ROOT: [u8; 32] = [1; 32];
+    const UPLOADED_ROOT: [u8; 32] = [2; 32];
+
+    fn upload_log(epoch: u64, storage_root: [u8; 32]) -> Log {
+        Log {
+            topics: vec![DataUploadFilter::signature()],
+            data: abi::encode(&[
+                Token::Address(H160::zero()),
+                Token::FixedBytes(storage_root.to_vec()),
+                Token::Uint(epoch.into()),
+                Token::Uint(0.into()),
+                Token::Uint(0.into()),
+            ])
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    fn verified_log(epoch: u64, storage_root: [u8; 32]) -> Log {
+        Log {
+            topics: vec![ErasureCommitmentVerifiedFilter::signature()],
+            data: abi::encode(&[
+                Token::FixedBytes(storage_root.to_vec()),
+                Token::Uint(epoch.into()),
+                Token::Uint(0.into()),
+            ])
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    async fn export() -> MetadataExport {
+        let storage = Storage::in_memory();
+        storage
+            .put_blob(3, 0, VERIFIED_ROOT, BlobStatus::VERIFIED)
+            .await
+            .unwrap();
+        storage
+            .put_blob(4, 0, UPLOADED_ROOT, BlobStatus::UPLOADED)
+            .await
+            .unwrap();
+        storage.put_sync_progress(50).await.unwrap();
+        storage.export_metadata().await.unwrap()
+    }
+
+    // mocks the chain as read by `check_blobs` for an export at block 50, with `uploads` found
+    // by the search for the uploaded blob
+    async fn check(export: &MetadataExport, uploads: Vec<Log>) -> Result<()> {
+        let (provider, mock) = Provider::<MockProvider>::mocked();
+        // responses are popped from the back
+        mock.push::<Vec<Log>, _>(uploads).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(abi::encode(&[
+            Token::Uint(1.into()),
+            Token::Uint(2.into()),
+        ])))
+        .unwrap();
+        mock.push::<Vec<Log>, _>(vec![verified_log(3, VERIFIED_ROOT)])
+            .unwrap();
+        mock.push::<Vec<Log>, _>(vec![
+            upload_log(3, VERIFIED_ROOT),
+            upload_log(4, UPLOADED_ROOT),
+        ])
+        .unwrap();
+        mock.push::<Block<H256>, _>(Block {
+            number: Some(60.into()),
+            ..Default::default()
+        })
+        .unwrap();
+        check_blobs(
+            &DAEntrance::new(H160::repeat_byte(1), Arc::new(provider)),
+            export,
+        )
+        .await
+    }
+
+    #[test]
+    fn spot_check_sample_test() {
+        let items: Vec<usize> = (0..100).collect();
+        let sample: Vec<_> = spot_check_sample(&items).copied().collect();
+        assert_eq!(sample.len(), SPOT_CHECKS);
+        assert_eq!(sample[..3], [0, 3, 6]);
+        let items: Vec<usize> = (0..10).collect();
+        assert_eq!(spot_check_sample(&items).count(), 10);
+        assert_eq!(spot_check_sample::<usize>(&[]).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn bootstrap_test() {
+        let export = export().await;
+        check(&export, vec![upload_log(4, UPLOADED_ROOT)])
+            .await
+            .unwrap();
+        let storage = Storage::in_memory();
+        storage.import_metadata(&export, false).await.unwrap();
+        assert_eq!(storage.export_metadata().await.unwrap(), export);
+
+        // uploaded blobs without an upload on chain are refused, the search stopping at uploads
+        // of earlier epochs
+        let e = check(&export, vec![upload_log(2, [9; 32])])
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("no DataUpload event"));
+    }
+}
@@ -8,23 +8,19 @@ use ethers::{abi::RawLog, prelude::EthLogDecode, providers::Middleware, types::B
 use storage::{
     blob_status_db::{BlobStatus, BlobStatusDB},
     misc_db::MiscDB,
+    Storage,
 };
 use tokio::time::sleep;
 use utils::metrics;
 
-const MAX_LOGS_PAGINATION: u64 = 100;
+pub(crate) const MAX_LOGS_PAGINATION: u64 = 100;
 
 pub async fn start_da_monitor(chain_state: Arc<ChainState>, start_block_number: u64) -> Result<()> {
-    let maybe_progress = chain_state.db.read().await.get_sync_progress().await?;
+    let maybe_progress = chain_state.db.get_sync_progress().await?;
     match maybe_progress {
         Some(_) => {}
         None => {
-            chain_state
-                .db
-                .write()
-                .await
-                .put_sync_progress(start_block_number)
-                .await?;
+            chain_state.db.put_sync_progress(start_block_number).await?;
         }
     }
     tokio::spawn(async move {
@@ -42,13 +38,7 @@ pub async fn start_da_monitor(chain_state: Arc<ChainState>, start_block_number:
 }
 
 async fn check_da_logs(chain_state: Arc<ChainState>) -> Result<()> {
-    let from = chain_state
-        .db
-        .read()
-        .await
-        .get_sync_progress()
-        .await?
-        .unwrap();
+    let from = chain_state.db.get_sync_progress().await?.unwrap();
     match chain_state
         .provider
         .get_block(BlockNumber::Finalized)
@@ -64,12 +54,7 @@ async fn check_da_logs(chain_state: Arc<ChainState>) -> Result<()> {
                     );
                     check_data_logs(chain_state.clone(), from, to).await?;
                     metrics::CHAIN_PROGRESS.set(to as f64);
-                    chain_state
-                        .db
-                        .write()
-                        .await
-                        .put_sync_progress(to + 1)
-                        .await?;
+                    chain_state.db.put_sync_progress(to + 1).await?;
                 }
             } else {
                 bail!(anyhow!("block number is empty"));
@@ -93,6 +78,26 @@ async fn check_data_logs(chain_state: Arc<ChainState>, from: u64, to: u64) -> Re
     Ok(())
 }
 
+/// Records the status of a blob from a log, returning whether it changed. Blobs only go from
+/// uploaded to verified, whatever order their logs are read in.
+async fn update_blob_status(
+    db: &Storage,
+    epoch: u64,
+    quorum_id: u64,
+    data_root: [u8; 32],
+    status: BlobStatus,
+) -> Result<bool> {
+    let current = db.get_blob_status(epoch, quorum_id, data_root).await?;
+    let update = match status {
+        BlobStatus::UPLOADED => current.is_none(),
+        BlobStatus::VERIFIED => current != Some(BlobStatus::VERIFIED),
+    };
+    if update {
+        db.put_blob(epoch, quorum_id, data_root, status).await?;
+    }
+    Ok(update)
+}
+
 async fn check_data_upload(chain_state: Arc<ChainState>, l: u64, r: u64) -> Result<()> {
     let filter: ethers::types::Filter = chain_state
         .da_entrance
@@ -109,26 +114,19 @@ async fn check_data_upload(chain_state: Arc<ChainState>, l: u64, r: u64) -> Resu
             Ok(event) => {
                 let epoch = event.epoch.as_u64();
                 let quorum_id = event.quorum_id.as_u64();
-                let maybe_blob_status = chain_state
-                    .db
-                    .read()
-                    .await
-                    .get_blob_status(epoch, quorum_id, event.data_root)
-                    .await?;
-                match maybe_blob_status {
-                    Some(_) => {}
-                    None => {
-                        chain_state
-                            .db
-                            .write()
-                            .await
-                            .put_blob(epoch, quorum_id, event.data_root, BlobStatus::UPLOADED)
-                            .await?;
-                        info!(
-                            "new file found, epoch: {:?}, quorum_id: {:?}, data_root: {:X?}",
-                            epoch, quorum_id, event.data_root
-                        );
-                    }
+                if update_blob_status(
+                    &chain_state.db,
+                    epoch,
+                    quorum_id,
+                    event.data_root,
+                    BlobStatus::UPLOADED,
+                )
+                .await?
+                {
+                    info!(
+                        "new file found, epoch: {:?}, quorum_id: {:?}, data_root: {:X?}",
+                        epoch, quorum_id, event.data_root
+                    );
                 }
             }
             Err(e) => {
@@ -155,23 +153,15 @@ async fn check_data_verified(chain_state: Arc<ChainState>, l: u64, r: u64) -> Re
             Ok(event) => {
                 let epoch = event.epoch.as_u64();
                 let quorum_id = event.quorum_id.as_u64();
-                let maybe_blob_status = chain_state
-                    .db
-                    .read()
-                    .await
-                    .get_blob_status(epoch, quorum_id, event.data_root)
-                    .await?;
-                let mut need_write = true;
-                if let Some(BlobStatus::VERIFIED) = maybe_blob_status {
-                    need_write = false;
-                }
-                if need_write {
-                    chain_state
-                        .db
-                        .write()
-                        .await
-                        .put_blob(epoch, quorum_id, event.data_root, BlobStatus::VERIFIED)
-                        .await?;
+                if update_blob_status(
+                    &chain_state.db,
+                    epoch,
+                    quorum_id,
+                    event.data_root,
+                    BlobStatus::VERIFIED,
+                )
+                .await?
+                {
                     info!(
                         "file verified, epoch: {:?}, quorum_id: {:?}, data_root: {:X?}",
                         epoch, quorum_id, event.data_root
@@ -185,3 +175,28 @@ async fn check_data_verified(chain_state: Arc<ChainState>, l: u64, r: u64) -> Re
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn update_blob_status_test() {
+        let db = Storage::in_memory();
+        let update = |root: u8, status| update_blob_status(&db, 1, 0, [root; 32], status);
+        assert!(update(1, BlobStatus::UPLOADED).await.unwrap());
+        assert!(!update(1, BlobStatus::UPLOADED).await.unwrap());
+        assert!(update(1, BlobStatus::VERIFIED).await.unwrap());
+        assert!(!update(1, BlobStatus::VERIFIED).await.unwrap());
+        // an upload log read after the verification keeps the blob verified
+        assert!(!update(1, BlobStatus::UPLOADED).await.unwrap());
+        assert!(update(2, BlobStatus::VERIFIED).await.unwrap());
+        assert!(!update(2, BlobStatus::UPLOADED).await.unwrap());
+        for root in [1, 2] {
+            assert_eq!(
+                db.get_blob_status(1, 0, [root; 32]).await.unwrap(),
+                Some(BlobStatus::VERIFIED)
+            );
+        }
+    }
+}
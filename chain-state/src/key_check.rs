@@ -0,0 +1,164 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use contract_interface::da_signers::SignerDetail;
+use key_signer::{BlsKeyRing, BlsSigner};
+use tokio::time::sleep;
+use utils::metrics;
+
+use crate::{
+    signers_handler::{serialize_g1_point, serialize_g2_point},
+    ChainState,
+};
+
+const KEY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+fn is_registered_key(detail: &SignerDetail, bls_signer: &dyn BlsSigner) -> bool {
+    let pk_g1 = serialize_g1_point(bls_signer.public_key_g1());
+    let pk_g2 = serialize_g2_point(bls_signer.public_key_g2());
+    detail.pk_g1.x == pk_g1.x
+        && detail.pk_g1.y == pk_g1.y
+        && detail.pk_g2.x == pk_g2.x
+        && detail.pk_g2.y == pk_g2.y
+}
+
+// the next key signs once it is registered out of band, as the contracts cannot replace a key
+fn record_next_key(detail: &SignerDetail, keys: &BlsKeyRing) {
+    if let Some((_, next)) = keys.rotation() {
+        keys.set_next_registered(is_registered_key(detail, &**next));
+    }
+}
+
+// the key of the next epoch may already be registered during a key rotation
+fn is_registered_key_at(detail: &SignerDetail, keys: &BlsKeyRing, epoch: u64) -> bool {
+    is_registered_key(detail, &**keys.at(epoch)) || is_registered_key(detail, &**keys.at(epoch + 1))
+}
+
+impl ChainState {
+    async fn signer_detail(&self) -> Result<SignerDetail> {
+        match self
+            .da_signers
+            .get_signer(vec![self.signer_address])
+            .call()
+            .await?
+            .pop()
+        {
+            Some(detail) => Ok(detail),
+            None => bail!("cannot get signer from precompile!"),
+        }
+    }
+
+    /// Switches to the next key of a scheduled rotation once the chain reports it for the signer.
+    pub async fn refresh_next_key(&self, keys: &BlsKeyRing, epoch: u64) -> Result<()> {
+        let rotation_epoch = match keys.rotation() {
+            Some((rotation_epoch, _)) => rotation_epoch,
+            None => return Ok(()),
+        };
+        let detail = self.signer_detail().await?;
+        record_next_key(&detail, keys);
+        if epoch >= rotation_epoch && !is_registered_key(&detail, &**keys.at(epoch)) {
+            warn!(
+                "bls key rotation epoch {:?} reached, but the next key of signer {:?} is not \
+                 registered on chain yet",
+                rotation_epoch, self.signer_address
+            );
+        }
+        Ok(())
+    }
+
+    /// Compares the public key registered on chain with the local key, which may be the key of
+    /// the current or the next epoch during a key rotation.
+    pub async fn check_registered_key(&self, keys: &BlsKeyRing) -> Result<()> {
+        let epoch = self.da_signers.epoch_number().call().await?.as_u64();
+        let detail = self.signer_detail().await?;
+        record_next_key(&detail, keys);
+        let mismatch = if is_registered_key_at(&detail, keys, epoch) {
+            None
+        } else {
+            Some(format!(
+                "local bls key differs from the one registered on chain for signer {:?}, \
+                 check `signer_bls_private_key`",
+                self.signer_address
+            ))
+        };
+        if let Some(e) = &mismatch {
+            error!("{}", e);
+        }
+        metrics::BLS_KEY_MISMATCH.set(mismatch.is_some() as i64);
+        *self.key_mismatch.write().await = mismatch;
+        Ok(())
+    }
+
+    /// The error to refuse signing requests with, if the local key mismatches the registered one.
+    pub async fn key_mismatch(&self) -> Option<String> {
+        self.key_mismatch.read().await.clone()
+    }
+}
+
+pub fn start_key_check(chain_state: Arc<ChainState>, keys: Arc<BlsKeyRing>) {
+    tokio::spawn(async move {
+        loop {
+            sleep(KEY_CHECK_INTERVAL).await;
+            if let Err(e) = chain_state.check_registered_key(&keys).await {
+                error!("check registered bls key error: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use contract_interface::da_signers::{G1Point, G2Point};
+    use ethers::types::H160;
+    use key_signer::LocalBlsSigner;
+
+    fn signer_detail(bls_signer: &dyn BlsSigner) -> SignerDetail {
+        let pk_g1 = serialize_g1_point(bls_signer.public_key_g1());
+        let pk_g2 = serialize_g2_point(bls_signer.public_key_g2());
+        SignerDetail {
+            signer: H160::zero(),
+            socket: String::new(),
+            pk_g1: G1Point {
+                x: pk_g1.x,
+                y: pk_g1.y,
+            },
+            pk_g2: G2Point {
+                x: pk_g2.x,
+                y: pk_g2.y,
+            },
+        }
+    }
+
+    #[test]
+    fn registered_key_rotation_test() {
+        let current = Arc::new(LocalBlsSigner::new(Fr::from(3u64)));
+        let next = Arc::new(LocalBlsSigner::new(Fr::from(5u64)));
+        let keys = BlsKeyRing::new(current.clone()).with_next(next.clone(), 10);
+        let current_detail = signer_detail(&*current);
+        let next_detail = signer_detail(&*next);
+
+        // the current key signs on while the chain reports it
+        record_next_key(&current_detail, &keys);
+        assert!(is_registered_key_at(&current_detail, &keys, 10));
+        assert!(!is_registered_key_at(&next_detail, &keys, 10));
+
+        // once the next key is registered it is expected from the rotation epoch on
+        record_next_key(&next_detail, &keys);
+        // before the rotation epoch the current key is expected, and in the epoch before it the
+        // next key may already be registered
+        assert!(is_registered_key_at(&current_detail, &keys, 5));
+        assert!(!is_registered_key_at(&next_detail, &keys, 5));
+        assert!(is_registered_key_at(&current_detail, &keys, 9));
+        assert!(is_registered_key_at(&next_detail, &keys, 9));
+        // at and after the rotation epoch only the next key is expected
+        assert!(!is_registered_key_at(&current_detail, &keys, 10));
+        assert!(is_registered_key_at(&next_detail, &keys, 10));
+        assert!(!is_registered_key_at(&current_detail, &keys, 11));
+        assert!(is_registered_key_at(&next_detail, &keys, 11));
+
+        let other = LocalBlsSigner::new(Fr::from(7u64));
+        assert!(!is_registered_key_at(&signer_detail(&other), &keys, 9));
+    }
+}
@@ -1,7 +1,13 @@
 #[macro_use]
 extern crate tracing;
 
+pub mod balance_monitor;
+pub mod bootstrap;
 pub mod da_handler;
+pub mod key_check;
+pub mod quorum_handler;
+pub mod registration_tracker;
+pub mod signature_check;
 pub mod signers_handler;
 pub mod transactor;
 
@@ -19,6 +25,7 @@ use reqwest::Url;
 use storage::Storage;
 use tokio::sync::{Mutex, RwLock};
 use transactor::Transactor;
+use utils::hash_to_curve::G1HashScheme;
 
 pub struct ChainState {
     provider: Arc<Provider<RetryClient<Http>>>,
@@ -27,15 +34,22 @@ pub struct ChainState {
     pub da_registry: Arc<DARegistry<Provider<RetryClient<Http>>>>,
     transactor: Arc<Mutex<Transactor>>,
     signer_address: H160,
-    db: Arc<RwLock<Storage>>,
+    multicall_address: Option<H160>,
+    quorum_fetch_lock: Mutex<()>,
+    db: Arc<Storage>,
+    // set while the local bls key mismatches the registered one
+    key_mismatch: RwLock<Option<String>>,
+    hash_scheme: G1HashScheme,
 }
 
 impl ChainState {
     pub async fn new(
         eth_rpc_url: &str,
         da_entrance_address: H160,
+        multicall_address: Option<H160>,
         transactor: Arc<Mutex<Transactor>>,
-        db: Arc<RwLock<Storage>>,
+        db: Arc<Storage>,
+        hash_scheme: G1HashScheme,
     ) -> Result<Self> {
         let client = reqwest::ClientBuilder::default()
             .timeout(Duration::from_secs(60))
@@ -61,7 +75,11 @@ impl ChainState {
             da_registry,
             transactor,
             signer_address,
+            multicall_address,
+            quorum_fetch_lock: Mutex::new(()),
             db,
+            key_mismatch: RwLock::new(None),
+            hash_scheme,
         })
     }
 }
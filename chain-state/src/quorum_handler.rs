@@ -0,0 +1,261 @@
+use std::{cmp, ops::Range, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Result};
+use contract_interface::DASigners;
+use ethers::{contract::Multicall, providers::Middleware, types::H160, types::U256};
+use futures::{stream, StreamExt, TryStreamExt};
+use storage::quorum_db::{AssignedSlices, QuorumDB};
+use tokio::time::sleep;
+use utils::metrics;
+
+use crate::ChainState;
+
+const QUORUM_PREFETCH_INTERVAL: Duration = Duration::from_secs(1);
+const MULTICALL_BATCH_SIZE: u64 = 8;
+// quorums read at once without multicall
+const QUORUM_FETCH_CONCURRENCY: usize = 8;
+
+// quorum ids fetched in each multicall
+fn quorum_batches(quorum_cnt: u64) -> impl Iterator<Item = Range<u64>> {
+    (0..quorum_cnt)
+        .step_by(MULTICALL_BATCH_SIZE as usize)
+        .map(move |start| start..cmp::min(start + MULTICALL_BATCH_SIZE, quorum_cnt))
+}
+
+// the row indices of `signer` in each quorum
+fn assigned_slices(quorums: Vec<Vec<H160>>, signer: H160) -> Vec<AssignedSlices> {
+    quorums
+        .into_iter()
+        .map(|quorum| {
+            AssignedSlices(
+                quorum
+                    .into_iter()
+                    .enumerate()
+                    .filter(|&(_, s)| s == signer)
+                    .map(|(idx, _)| idx as u64)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+impl ChainState {
+    pub async fn fetch_quorum_if_missing(&self, epoch: u64) -> Result<u64> {
+        let max_epoch = (self.da_signers.epoch_number().call().await?).as_u64();
+        if max_epoch < epoch {
+            bail!(anyhow!("invalid epoch"));
+        }
+        self.fetch_quorum(epoch).await
+    }
+
+    async fn fetch_quorum(&self, epoch: u64) -> Result<u64> {
+        // the prefetcher and the sign path may ask for the same epoch at the same time
+        let _guard = self.quorum_fetch_lock.lock().await;
+        let maybe_quorum_num = self.db.get_quorum_num(epoch).await?;
+        match maybe_quorum_num {
+            Some(cnt) => Ok(cnt),
+            None => {
+                info!("updating quorums of epoch: {:?}", epoch);
+                let quorum_cnt = (self
+                    .da_signers
+                    .quorum_count(U256::from(epoch))
+                    .call()
+                    .await?)
+                    .as_u64();
+                metrics::EPOCH_QUORUMS.set(quorum_cnt as i64);
+                let assigned = self.get_assigned_slices(epoch, quorum_cnt).await?;
+                self.db.put_quorums(epoch, assigned).await?;
+                Ok(quorum_cnt)
+            }
+        }
+    }
+
+    /// The slices assigned to this signer in each quorum of the epoch, as recorded on chain.
+    pub(crate) async fn get_assigned_slices(
+        &self,
+        epoch: u64,
+        quorum_cnt: u64,
+    ) -> Result<Vec<AssignedSlices>> {
+        Ok(assigned_slices(
+            self.get_quorums(epoch, quorum_cnt).await?,
+            self.signer_address,
+        ))
+    }
+
+    async fn get_quorums(&self, epoch: u64, quorum_cnt: u64) -> Result<Vec<Vec<H160>>> {
+        get_quorums(&self.da_signers, self.multicall_address, epoch, quorum_cnt).await
+    }
+}
+
+// a quorum is read in one call, `getQuorumRow` would take a call per slice
+async fn get_quorums<M: Middleware + 'static>(
+    da_signers: &DASigners<M>,
+    multicall_address: Option<H160>,
+    epoch: u64,
+    quorum_cnt: u64,
+) -> Result<Vec<Vec<H160>>> {
+    let mut quorums = vec![];
+    match multicall_address {
+        Some(address) => {
+            let mut multicall = Multicall::new(da_signers.client(), Some(address)).await?;
+            for batch in quorum_batches(quorum_cnt) {
+                multicall.clear_calls();
+                for quorum_id in batch {
+                    multicall.add_call(
+                        da_signers.get_quorum(U256::from(epoch), U256::from(quorum_id)),
+                        false,
+                    );
+                }
+                let batch_quorums: Vec<Vec<H160>> = multicall.call_array().await?;
+                quorums.extend(batch_quorums);
+            }
+        }
+        None => {
+            quorums = stream::iter(0..quorum_cnt)
+                .map(|quorum_id| async move {
+                    da_signers
+                        .get_quorum(U256::from(epoch), U256::from(quorum_id))
+                        .call()
+                        .await
+                })
+                .buffered(QUORUM_FETCH_CONCURRENCY)
+                .try_collect()
+                .await?;
+        }
+    }
+    Ok(quorums)
+}
+
+// the quorums of an epoch are readable once they are made, which may be before the epoch starts
+async fn quorums_readable<M: Middleware + 'static>(da_signers: &DASigners<M>, epoch: u64) -> bool {
+    matches!(
+        da_signers.quorum_count(U256::from(epoch)).call().await,
+        Ok(quorum_cnt) if !quorum_cnt.is_zero()
+    )
+}
+
+pub fn start_quorum_prefetch(chain_state: Arc<ChainState>) {
+    tokio::spawn(async move {
+        let mut prefetched = None;
+        loop {
+            match prefetch_quorum(chain_state.clone(), prefetched).await {
+                Ok(epoch) => {
+                    prefetched = epoch;
+                }
+                Err(e) => {
+                    error!("prefetch quorum error: {:?}", e);
+                }
+            }
+            sleep(QUORUM_PREFETCH_INTERVAL).await;
+        }
+    });
+}
+
+// returns the last epoch whose quorums are fetched, the current or the next one
+async fn prefetch_quorum(
+    chain_state: Arc<ChainState>,
+    prefetched: Option<u64>,
+) -> Result<Option<u64>> {
+    let epoch = chain_state.da_signers.epoch_number().call().await?.as_u64();
+    let mut prefetched = prefetched;
+    if prefetched < Some(epoch) {
+        metrics::QUORUM_CACHE_READY.set(0);
+        chain_state.fetch_quorum(epoch).await?;
+        info!("quorums of epoch {:?} are ready", epoch);
+        metrics::QUORUM_CACHE_READY.set(1);
+        metrics::QUORUM_READY_EPOCH.set(epoch as i64);
+        prefetched = Some(epoch);
+    }
+    // the next epoch is fetched ahead, so that its first sign requests do not wait for it
+    let next_epoch = epoch + 1;
+    if prefetched == Some(epoch) && quorums_readable(&chain_state.da_signers, next_epoch).await {
+        chain_state.fetch_quorum(next_epoch).await?;
+        info!("quorums of next epoch {:?} are ready", next_epoch);
+        metrics::QUORUM_READY_EPOCH.set(next_epoch as i64);
+        prefetched = Some(next_epoch);
+    }
+    Ok(prefetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{self, Token},
+        providers::{MockProvider, Provider},
+        types::Bytes,
+    };
+
+    fn da_signers() -> (DASigners<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::<MockProvider>::mocked();
+        (
+            DASigners::new(H160::repeat_byte(1), Arc::new(provider)),
+            mock,
+        )
+    }
+
+    fn quorum(signers: &[H160]) -> Bytes {
+        abi::encode(&[Token::Array(
+            signers.iter().map(|x| Token::Address(*x)).collect(),
+        )])
+        .into()
+    }
+
+    #[tokio::test]
+    async fn get_quorums_test() {
+        let (da_signers, mock) = da_signers();
+        let signers = [H160::repeat_byte(2), H160::repeat_byte(3)];
+        // responses are popped from the back
+        for signer in signers.iter().rev() {
+            mock.push::<Bytes, _>(quorum(&[*signer, *signer])).unwrap();
+        }
+        assert_eq!(
+            get_quorums(&da_signers, None, 1, 2).await.unwrap(),
+            vec![vec![signers[0]; 2], vec![signers[1]; 2]]
+        );
+    }
+
+    fn quorum_count(quorum_cnt: u64) -> Bytes {
+        abi::encode(&[Token::Uint(quorum_cnt.into())]).into()
+    }
+
+    #[tokio::test]
+    async fn quorums_readable_test() {
+        let (da_signers, mock) = da_signers();
+        mock.push::<Bytes, _>(quorum_count(0)).unwrap();
+        assert!(!quorums_readable(&da_signers, 2).await);
+        mock.push::<Bytes, _>(quorum_count(3)).unwrap();
+        assert!(quorums_readable(&da_signers, 2).await);
+        // nothing to answer with
+        assert!(!quorums_readable(&da_signers, 2).await);
+    }
+
+    #[test]
+    fn quorum_batches_test() {
+        assert_eq!(quorum_batches(0).count(), 0);
+        assert_eq!(quorum_batches(8).collect::<Vec<_>>(), vec![0..8]);
+        assert_eq!(
+            quorum_batches(19).collect::<Vec<_>>(),
+            vec![0..8, 8..16, 16..19]
+        );
+    }
+
+    #[test]
+    fn assigned_slices_test() {
+        let signer = H160::repeat_byte(1);
+        let other = H160::repeat_byte(2);
+        let quorums = vec![
+            vec![signer, other, signer, other],
+            vec![other, other, other, other],
+            vec![other, signer, other, other],
+        ];
+        assert_eq!(
+            assigned_slices(quorums, signer),
+            vec![
+                AssignedSlices(vec![0, 2]),
+                AssignedSlices(vec![]),
+                AssignedSlices(vec![1]),
+            ]
+        );
+    }
+}
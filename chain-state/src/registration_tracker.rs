@@ -0,0 +1,192 @@
+use anyhow::Result;
+use ethers::{providers::Middleware, types::U256};
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use utils::metrics;
+
+use crate::ChainState;
+
+const DEFAULT_REGISTRATION_ALERT_BLOCKS: u64 = 100;
+const FAILURES_BEFORE_WARNING: u64 = 3;
+
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationAlertConfig {
+    pub webhook_url: Option<String>,
+    pub alert_blocks: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum AlertLevel {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Tracks how close the node is to missing the registration of the next epoch.
+///
+/// Epochs are made at block heights divisible by `epochBlocks`, so a signer which is not
+/// registered for the next epoch by then drops out of its quorums.
+pub struct RegistrationDeadlineTracker {
+    webhook_url: Option<String>,
+    alert_blocks: u64,
+    client: reqwest::Client,
+    epoch_blocks: Option<u64>,
+    consecutive_failures: u64,
+    // the next epoch we are not registered for yet, and the highest level alerted for it
+    pending_epoch: Option<(u64, Option<AlertLevel>)>,
+}
+
+impl RegistrationDeadlineTracker {
+    pub fn new(config: RegistrationAlertConfig) -> Self {
+        Self {
+            webhook_url: config.webhook_url,
+            alert_blocks: config
+                .alert_blocks
+                .unwrap_or(DEFAULT_REGISTRATION_ALERT_BLOCKS),
+            client: reqwest::Client::new(),
+            epoch_blocks: None,
+            consecutive_failures: 0,
+            pending_epoch: None,
+        }
+    }
+
+    pub async fn update(
+        &mut self,
+        chain_state: &ChainState,
+        registration_succeeded: bool,
+    ) -> Result<()> {
+        if registration_succeeded {
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+        }
+        metrics::REGISTRATION_FAILURES.set(self.consecutive_failures as i64);
+
+        let epoch_blocks = match self.epoch_blocks {
+            Some(x) => x,
+            None => {
+                let x = chain_state
+                    .da_signers
+                    .params()
+                    .call()
+                    .await?
+                    .epoch_blocks
+                    .as_u64();
+                self.epoch_blocks = Some(x);
+                x
+            }
+        };
+        let block_number = chain_state.provider.get_block_number().await?.as_u64();
+        let blocks_remaining = blocks_until_next_epoch(block_number, epoch_blocks);
+        metrics::REGISTRATION_DEADLINE_BLOCKS.set(blocks_remaining as i64);
+
+        let epoch = chain_state.da_signers.epoch_number().call().await?.as_u64();
+        if let Some((pending, _)) = self.pending_epoch {
+            if pending <= epoch {
+                self.pending_epoch = None;
+                if !self.is_registered(chain_state, pending).await? {
+                    let message = format!(
+                        "signer {:?} missed the registration of epoch {:?}",
+                        chain_state.signer_address, pending
+                    );
+                    error!("{}", message);
+                    self.post_alert(&message, pending, 0).await;
+                }
+            }
+        }
+
+        let next_epoch = epoch + 1;
+        if self.is_registered(chain_state, next_epoch).await? {
+            self.pending_epoch = None;
+            return Ok(());
+        }
+
+        let level = if blocks_remaining <= self.alert_blocks {
+            AlertLevel::Critical
+        } else if blocks_remaining <= epoch_blocks / 2
+            || self.consecutive_failures >= FAILURES_BEFORE_WARNING
+        {
+            AlertLevel::Warning
+        } else {
+            AlertLevel::Info
+        };
+        let message = format!(
+            "signer {:?} is not registered for epoch {:?}, {:?} blocks until deadline, {:?} consecutive failures",
+            chain_state.signer_address, next_epoch, blocks_remaining, self.consecutive_failures
+        );
+        match level {
+            AlertLevel::Info => info!("{}", message),
+            AlertLevel::Warning => warn!("{}", message),
+            AlertLevel::Critical => error!("{}", message),
+        }
+
+        let alerted = match self.pending_epoch {
+            Some((pending, alerted)) if pending == next_epoch => alerted,
+            _ => None,
+        };
+        if level == AlertLevel::Critical && alerted < Some(AlertLevel::Critical) {
+            self.post_alert(&message, next_epoch, blocks_remaining)
+                .await;
+        }
+        self.pending_epoch = Some((next_epoch, alerted.max(Some(level))));
+        Ok(())
+    }
+
+    async fn is_registered(&self, chain_state: &ChainState, epoch: u64) -> Result<bool> {
+        Ok(chain_state
+            .da_signers
+            .registered_epoch(chain_state.signer_address, U256::from(epoch))
+            .call()
+            .await?)
+    }
+
+    async fn post_alert(&self, message: &str, epoch: u64, blocks_remaining: u64) {
+        let url = match &self.webhook_url {
+            Some(url) => url,
+            None => return,
+        };
+        let body = json!({
+            "text": message,
+            "epoch": epoch,
+            "blocks_remaining": blocks_remaining,
+        });
+        match self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+        {
+            Ok(resp) => {
+                if !resp.status().is_success() {
+                    warn!("registration alert webhook returns {:?}", resp.status());
+                }
+            }
+            Err(e) => {
+                warn!("failed to post registration alert: {:?}", e);
+            }
+        }
+    }
+}
+
+fn blocks_until_next_epoch(block_number: u64, epoch_blocks: u64) -> u64 {
+    if epoch_blocks == 0 {
+        return 0;
+    }
+    epoch_blocks - block_number % epoch_blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_until_next_epoch_test() {
+        assert_eq!(blocks_until_next_epoch(0, 100), 100);
+        assert_eq!(blocks_until_next_epoch(1, 100), 99);
+        assert_eq!(blocks_until_next_epoch(99, 100), 1);
+        assert_eq!(blocks_until_next_epoch(100, 100), 100);
+        assert_eq!(blocks_until_next_epoch(5, 0), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_serialize::CanonicalDeserialize;
+use ethers::types::{Bytes, U256};
+use key_signer::BlsKeyRing;
+use utils::blob_verified_hash;
+
+use crate::{signers_handler::serialize_g1_point, ChainState};
+
+/// Result of checking a blob signature of this signer against the chain.
+#[derive(Debug, Clone)]
+pub struct SignatureCheck {
+    // the pairing check against the registered G2 key passes
+    pub signature_valid: bool,
+    // rows of the quorum assigned to this signer
+    pub rows: u64,
+    // rows the contract counted when aggregating them
+    pub hit: u64,
+    // the aggregated G1 key of our rows equals the local key times the row count
+    pub aggregate_matches: bool,
+}
+
+fn u256_to_fq(value: U256) -> Result<Fq> {
+    let mut bytes = [0u8; 32];
+    value.to_little_endian(&mut bytes);
+    Fq::deserialize_uncompressed(&bytes[..]).map_err(|e| anyhow!("invalid field element: {:?}", e))
+}
+
+/// Inverse of `serialize_g2_point`.
+pub fn deserialize_g2_point(x: [U256; 2], y: [U256; 2]) -> Result<G2Affine> {
+    let point = G2Affine::new_unchecked(
+        Fq2::new(u256_to_fq(x[0])?, u256_to_fq(x[1])?),
+        Fq2::new(u256_to_fq(y[0])?, u256_to_fq(y[1])?),
+    );
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        bail!("G2 point is not in group");
+    }
+    Ok(point)
+}
+
+/// Checks e(signature, g2) == e(hash, pk_g2).
+pub fn verify_bls_signature(hash: G1Affine, signature: G1Affine, pk_g2: G2Affine) -> bool {
+    Bn254::pairing(signature, G2Affine::generator()) == Bn254::pairing(hash, pk_g2)
+}
+
+/// Bitmap of quorum rows, with row `i` at bit `i % 8` of byte `i / 8`.
+pub fn quorum_bitmap(rows: &[u64], row_count: usize) -> Vec<u8> {
+    let mut bitmap = vec![0u8; row_count.div_ceil(8)];
+    for row in rows {
+        bitmap[*row as usize / 8] |= 1 << (row % 8);
+    }
+    bitmap
+}
+
+impl ChainState {
+    /// Verifies a blob signature with the G2 key registered on chain, and checks that the
+    /// contract aggregates the rows of this signer in the quorum to the local G1 key of the epoch.
+    pub async fn check_signature(
+        &self,
+        keys: &BlsKeyRing,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        erasure_commitment: G1Projective,
+        signature: G1Affine,
+    ) -> Result<SignatureCheck> {
+        let detail = match self
+            .da_signers
+            .get_signer(vec![self.signer_address])
+            .call()
+            .await?
+            .pop()
+        {
+            Some(detail) => detail,
+            None => bail!("cannot get signer from precompile!"),
+        };
+        let registered_pk_g2 = deserialize_g2_point(detail.pk_g2.x, detail.pk_g2.y)?;
+        let hash = blob_verified_hash(
+            storage_root,
+            epoch,
+            quorum_id,
+            erasure_commitment,
+            self.hash_scheme,
+        );
+        let signature_valid = verify_bls_signature(hash, signature, registered_pk_g2);
+
+        let quorum = self
+            .da_signers
+            .get_quorum(U256::from(epoch), U256::from(quorum_id))
+            .call()
+            .await?;
+        let rows: Vec<u64> = quorum
+            .iter()
+            .enumerate()
+            .filter(|(_, signer)| **signer == self.signer_address)
+            .map(|(idx, _)| idx as u64)
+            .collect();
+        let (agg_pk_g1, _, hit) = self
+            .da_signers
+            .get_agg_pk_g1(
+                U256::from(epoch),
+                U256::from(quorum_id),
+                Bytes::from(quorum_bitmap(&rows, quorum.len())),
+            )
+            .call()
+            .await?;
+        let expected = serialize_g1_point(
+            (keys.at(epoch).public_key_g1() * Fr::from(rows.len() as u64)).into_affine(),
+        );
+        let aggregate_matches = !rows.is_empty()
+            && agg_pk_g1.x == expected.x
+            && agg_pk_g1.y == expected.y
+            && hit.as_u64() == rows.len() as u64;
+        if !signature_valid || !aggregate_matches {
+            warn!(
+                "signature check of epoch {:?} quorum {:?} failed: signature valid {:?}, \
+                 rows {:?}, hit {:?}, aggregate matches {:?}",
+                epoch,
+                quorum_id,
+                signature_valid,
+                rows.len(),
+                hit,
+                aggregate_matches
+            );
+        }
+        Ok(SignatureCheck {
+            signature_valid,
+            rows: rows.len() as u64,
+            hit: hit.as_u64(),
+            aggregate_matches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signers_handler::serialize_g2_point;
+
+    #[test]
+    fn verify_bls_signature_test() {
+        let key = Fr::from(11u64);
+        let pk_g2 = (G2Affine::generator() * key).into_affine();
+        let hash = (G1Affine::generator() * Fr::from(5u64)).into_affine();
+        let signature = (hash * key).into_affine();
+        assert!(verify_bls_signature(hash, signature, pk_g2));
+        let other_pk_g2 = (G2Affine::generator() * Fr::from(12u64)).into_affine();
+        assert!(!verify_bls_signature(hash, signature, other_pk_g2));
+
+        let serialized = serialize_g2_point(pk_g2);
+        assert_eq!(
+            deserialize_g2_point(serialized.x, serialized.y).unwrap(),
+            pk_g2
+        );
+    }
+
+    #[test]
+    fn quorum_bitmap_test() {
+        assert_eq!(quorum_bitmap(&[0, 3, 9], 10), vec![0b1001, 0b10]);
+        assert_eq!(quorum_bitmap(&[], 8), vec![0]);
+    }
+}
@@ -1,24 +1,37 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use anyhow::{anyhow, bail, Result};
-use ark_bn254::{g1, g2, Fr, G1Affine, G2Affine};
-use ark_ec::{AffineRepr, CurveGroup};
+use ark_bn254::{G1Affine, G2Affine};
+use ark_ec::AffineRepr;
 
 use ark_serialize::CanonicalSerialize;
-use contract_interface::da_registry::{G1Point, G2Point, SignerDetail};
+use contract_interface::{
+    da_registry::{G1Point, G2Point, RegisterNextEpochCall, RegisterSignerCall, SignerDetail},
+    da_signers::UpdateSocketCall,
+};
 
 use ethers::{
+    abi::AbiEncode,
     providers::Middleware,
-    types::{BlockNumber, TransactionRequest, H160, U256},
+    types::{BlockNumber, Bytes, TransactionRequest, H160, U256},
     utils::keccak256,
 };
+use key_signer::{BlsKeyRing, BlsSigner};
 
-use storage::quorum_db::{AssignedSlices, QuorumDB};
-
+use storage::registration_db::{
+    RegistrationDB, RegistrationKind, RegistrationOutcome, RegistrationRecord,
+};
 use tokio::time::sleep;
-use utils::{left_pad_zeros, map_to_g1, metrics};
+use utils::{hash_to_curve::G1HashScheme, left_pad_zeros, metrics};
 
-use crate::{transactor::TransactionInfo, ChainState};
+use crate::{
+    registration_tracker::{RegistrationAlertConfig, RegistrationDeadlineTracker},
+    transactor::{TransactionInfo, TransactionResult},
+    ChainState,
+};
 
 const PUBKEY_REGISTRATION_DOMAIN: &[u8] = "0G_BN254_Pubkey_Registration".as_bytes();
 
@@ -65,26 +78,70 @@ pub fn serialize_g2_point(point: G2Affine) -> G2Point {
     G2Point { x, y }
 }
 
-fn signer_registration_hash(signer_address: H160, chain_id: u64) -> G1Affine {
+pub fn signer_registration_hash(
+    signer_address: H160,
+    chain_id: u64,
+    hash_scheme: G1HashScheme,
+) -> G1Affine {
     let mut message = vec![];
     message.append(&mut signer_address.to_fixed_bytes().to_vec());
     message.append(&mut left_pad_zeros(chain_id, 32));
     message.append(&mut PUBKEY_REGISTRATION_DOMAIN.to_vec());
-    map_to_g1(keccak256(message).to_vec())
+    hash_scheme.hash(&keccak256(message))
 }
 
-fn epoch_registration_hash(signer_address: H160, epoch: u64, chain_id: u64) -> G1Affine {
+pub fn epoch_registration_hash(
+    signer_address: H160,
+    epoch: u64,
+    chain_id: u64,
+    hash_scheme: G1HashScheme,
+) -> G1Affine {
     let mut message = vec![];
     message.append(&mut signer_address.to_fixed_bytes().to_vec());
     message.append(&mut left_pad_zeros(epoch, 8));
     message.append(&mut left_pad_zeros(chain_id, 32));
-    map_to_g1(keccak256(message).to_vec())
+    hash_scheme.hash(&keccak256(message))
+}
+
+/// Calldata of `registerSigner` on the DA registry.
+pub fn register_signer_calldata(
+    signer: H160,
+    socket: String,
+    pk_g1: G1Affine,
+    pk_g2: G2Affine,
+    signature: G1Affine,
+) -> Bytes {
+    RegisterSignerCall {
+        signer: SignerDetail {
+            signer,
+            socket,
+            pk_g1: serialize_g1_point(pk_g1),
+            pk_g2: serialize_g2_point(pk_g2),
+        },
+        signature: serialize_g1_point(signature),
+    }
+    .encode()
+    .into()
+}
+
+/// Calldata of `registerNextEpoch` on the DA registry.
+pub fn register_next_epoch_calldata(signature: G1Affine) -> Bytes {
+    RegisterNextEpochCall {
+        signature: serialize_g1_point(signature),
+    }
+    .encode()
+    .into()
+}
+
+/// Calldata of `updateSocket` on the DA signers precompile.
+pub fn update_socket_calldata(socket: String) -> Bytes {
+    UpdateSocketCall { socket }.encode().into()
 }
 
 impl ChainState {
     pub async fn check_signer_registration(
         &self,
-        signer_bls_private_key: Fr,
+        bls_signer: &dyn BlsSigner,
         socket: String,
     ) -> Result<()> {
         if !self
@@ -93,61 +150,7 @@ impl ChainState {
             .call()
             .await?
         {
-            let signer_pub_key_g1 =
-                (g1::G1Affine::generator() * signer_bls_private_key).into_affine();
-            let signer_pub_key_g2 =
-                (g2::G2Affine::generator() * signer_bls_private_key).into_affine();
-            let hash = signer_registration_hash(
-                self.signer_address,
-                self.provider.get_chainid().await?.as_u64(),
-            );
-            let signature = (hash * signer_bls_private_key).into_affine();
-            let maybe_input_data = self
-                .da_registry
-                .register_signer(
-                    SignerDetail {
-                        signer: self.signer_address,
-                        socket: socket.clone(),
-                        pk_g1: serialize_g1_point(signer_pub_key_g1),
-                        pk_g2: serialize_g2_point(signer_pub_key_g2),
-                    },
-                    serialize_g1_point(signature),
-                )
-                .calldata();
-            if let Some(input_data) = maybe_input_data {
-                info!(
-                    "try to register signer: account {:?}, pubkey g1 {:?}, pubkey g2: {:?}, socket: {:?}",
-                    self.signer_address,
-                    signer_pub_key_g1,
-                    signer_pub_key_g2,
-                    socket,
-                );
-                let tx_request = TransactionRequest::new()
-                    .to(self.da_registry.address())
-                    .data(input_data);
-                match self
-                    .transactor
-                    .lock()
-                    .await
-                    .send(
-                        tx_request,
-                        TransactionInfo::RegisterSigner(self.signer_address),
-                    )
-                    .await
-                {
-                    Ok(success) => {
-                        if success {
-                            info!("signer registered");
-                            sleep(Duration::from_secs(10)).await;
-                        } else {
-                            bail!(anyhow!("register signer failed"));
-                        }
-                    }
-                    Err(e) => {
-                        bail!(anyhow!(e));
-                    }
-                }
-            }
+            self.register_signer(bls_signer, socket.clone()).await?;
         }
         match self
             .da_signers
@@ -162,11 +165,7 @@ impl ChainState {
                         "change socket of signer from {:?} to {:?}",
                         signer_detail.socket, socket
                     );
-                    let input_data = self
-                        .da_signers
-                        .update_socket(socket.clone())
-                        .calldata()
-                        .unwrap();
+                    let input_data = update_socket_calldata(socket.clone());
                     let tx_request = TransactionRequest::new()
                         .to(self.da_signers.address())
                         .data(input_data);
@@ -181,8 +180,8 @@ impl ChainState {
                         )
                         .await
                     {
-                        Ok(success) => {
-                            if success {
+                        Ok(result) => {
+                            if result.success {
                                 info!("socket updated to {:?}", socket.clone());
                                 return Ok(());
                             }
@@ -201,60 +200,121 @@ impl ChainState {
         Ok(())
     }
 
-    pub async fn fetch_quorum_if_missing(&self, epoch: u64) -> Result<u64> {
-        let max_epoch = (self.da_signers.epoch_number().call().await?).as_u64();
-        if max_epoch < epoch {
-            bail!(anyhow!("invalid epoch"));
-        }
-        let maybe_quorum_num = self.db.read().await.get_quorum_num(epoch).await?;
-        match maybe_quorum_num {
-            Some(cnt) => Ok(cnt),
-            None => {
-                info!("updating quorums of epoch: {:?}", epoch);
-                let quorum_cnt = (self
-                    .da_signers
-                    .quorum_count(U256::from(epoch))
-                    .call()
-                    .await?)
-                    .as_u32() as i32;
-                let mut assigned = vec![];
-                metrics::EPOCH_QUORUMS.set(quorum_cnt as i64);
-                for i in 0..quorum_cnt {
-                    let quorum = self
-                        .da_signers
-                        .get_quorum(U256::from(epoch), U256::from(i))
-                        .call()
-                        .await?;
-                    let assigned_slices: Vec<u64> = quorum
-                        .into_iter()
-                        .enumerate()
-                        .filter(|&(_, signer)| signer == self.signer_address)
-                        .map(|(idx, _)| idx as u64)
-                        .collect();
-                    assigned.push(AssignedSlices(assigned_slices));
+    async fn register_signer(&self, bls_signer: &dyn BlsSigner, socket: String) -> Result<()> {
+        let signer_pub_key_g1 = bls_signer.public_key_g1();
+        let signer_pub_key_g2 = bls_signer.public_key_g2();
+        let hash = signer_registration_hash(
+            self.signer_address,
+            self.provider.get_chainid().await?.as_u64(),
+            self.hash_scheme,
+        );
+        let signature = bls_signer.sign_registration(hash).await?;
+        let input_data = register_signer_calldata(
+            self.signer_address,
+            socket.clone(),
+            signer_pub_key_g1,
+            signer_pub_key_g2,
+            signature,
+        );
+        info!(
+            "try to register signer: account {:?}, pubkey g1 {:?}, pubkey g2: {:?}, socket: {:?}",
+            self.signer_address, signer_pub_key_g1, signer_pub_key_g2, socket,
+        );
+        let tx_request = TransactionRequest::new()
+            .to(self.da_registry.address())
+            .data(input_data);
+        let epoch = self.da_signers.epoch_number().call().await?.as_u64();
+        let result = self
+            .transactor
+            .lock()
+            .await
+            .send(
+                tx_request,
+                TransactionInfo::RegisterSigner(self.signer_address),
+            )
+            .await;
+        self.record_registration(RegistrationKind::Signer, epoch, signature, &result)
+            .await;
+        match result {
+            Ok(result) => {
+                if result.success {
+                    info!("signer registered");
+                    sleep(Duration::from_secs(10)).await;
+                } else {
+                    bail!(anyhow!("register signer failed"));
                 }
-                self.db.write().await.put_quorums(epoch, assigned).await?;
-                Ok(quorum_cnt as u64)
+            }
+            Err(e) => {
+                bail!(anyhow!(e));
             }
         }
+        Ok(())
+    }
+
+    async fn record_registration(
+        &self,
+        kind: RegistrationKind,
+        epoch: u64,
+        signature: G1Affine,
+        result: &Result<TransactionResult>,
+    ) {
+        let mut serialized_signature = Vec::new();
+        signature
+            .serialize_uncompressed(&mut serialized_signature)
+            .unwrap();
+        let (tx_hash, block_number, outcome) = match result {
+            Ok(result) => (
+                Some(result.tx_hash.0),
+                result.block_number,
+                if result.success {
+                    RegistrationOutcome::Success
+                } else {
+                    RegistrationOutcome::Reverted
+                },
+            ),
+            Err(e) => (None, None, RegistrationOutcome::Error(e.to_string())),
+        };
+        let record = RegistrationRecord {
+            kind,
+            epoch,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+            tx_hash,
+            block_number,
+            signature: serialized_signature,
+            outcome,
+        };
+        if let Err(e) = self.db.put_registration_record(record).await {
+            error!("failed to persist registration record: {:?}", e);
+        }
     }
 }
 
-pub fn start_epoch_registration(chain_state: Arc<ChainState>, signer_bls_private_key: Fr) {
+pub fn start_epoch_registration(
+    chain_state: Arc<ChainState>,
+    bls_keys: Arc<BlsKeyRing>,
+    alert_config: RegistrationAlertConfig,
+) {
     tokio::spawn(async move {
+        let mut tracker = RegistrationDeadlineTracker::new(alert_config);
         loop {
-            match check_epoch(chain_state.clone(), signer_bls_private_key).await {
-                Ok(_) => {}
+            let registration_succeeded = match check_epoch(chain_state.clone(), &bls_keys).await {
+                Ok(_) => true,
                 Err(e) => {
                     error!("poll check_new_epoch error: {:?}", e);
+                    false
                 }
+            };
+            if let Err(e) = tracker.update(&chain_state, registration_succeeded).await {
+                error!("update registration deadline error: {:?}", e);
             }
             sleep(Duration::from_secs(5)).await;
         }
     });
 }
 
-async fn check_epoch(chain_state: Arc<ChainState>, signer_bls_private_key: Fr) -> Result<()> {
+async fn check_epoch(chain_state: Arc<ChainState>, bls_keys: &BlsKeyRing) -> Result<()> {
     match chain_state
         .provider
         .get_block(BlockNumber::Finalized)
@@ -269,9 +329,7 @@ async fn check_epoch(chain_state: Arc<ChainState>, signer_bls_private_key: Fr) -
                     .call()
                     .await?)
                     .as_u64();
-                check_new_quorums(chain_state.clone(), epoch).await?;
-                check_new_registration(chain_state.clone(), signer_bls_private_key, epoch + 1)
-                    .await?;
+                check_new_registration(chain_state.clone(), bls_keys, epoch + 1).await?;
                 Ok(())
             } else {
                 bail!(anyhow!("block number is empty"));
@@ -285,7 +343,7 @@ async fn check_epoch(chain_state: Arc<ChainState>, signer_bls_private_key: Fr) -
 
 async fn check_new_registration(
     chain_state: Arc<ChainState>,
-    signer_bls_private_key: Fr,
+    bls_keys: &BlsKeyRing,
     next_epoch: u64,
 ) -> Result<()> {
     if !chain_state
@@ -294,63 +352,107 @@ async fn check_new_registration(
         .call()
         .await?
     {
+        chain_state.refresh_next_key(bls_keys, next_epoch).await?;
         info!("registering for next epoch: {:?}", next_epoch);
         let hash = epoch_registration_hash(
             chain_state.signer_address,
             next_epoch,
             chain_state.provider.get_chainid().await?.as_u64(),
+            chain_state.hash_scheme,
         );
-        let signature = (hash * signer_bls_private_key).into_affine();
-        let maybe_input_data = chain_state
-            .da_registry
-            .register_next_epoch(serialize_g1_point(signature))
-            .calldata();
-
-        if let Some(input_data) = maybe_input_data {
-            info!(
-                "try to register epoch: account {:?}, epoch: {:?}",
-                chain_state.signer_address, next_epoch
-            );
-            let tx_request = TransactionRequest::new()
-                .to(chain_state.da_registry.address())
-                .data(input_data);
-            match chain_state
-                .transactor
-                .lock()
-                .await
-                .send(
-                    tx_request,
-                    TransactionInfo::RegisterEpoch(chain_state.signer_address, next_epoch),
-                )
-                .await
-            {
-                Ok(success) => {
-                    if success {
-                        info!("epoch {:?} registered", next_epoch);
-                        metrics::REGISTERED_EPOCH.set(next_epoch as f64);
-                        return Ok(());
-                    }
-                    bail!(anyhow!(format!("register epoch {:?} failed", next_epoch)));
-                }
-                Err(e) => {
-                    bail!(anyhow!(e));
+        let signature = bls_keys.at(next_epoch).sign_registration(hash).await?;
+        let input_data = register_next_epoch_calldata(signature);
+        info!(
+            "try to register epoch: account {:?}, epoch: {:?}",
+            chain_state.signer_address, next_epoch
+        );
+        let tx_request = TransactionRequest::new()
+            .to(chain_state.da_registry.address())
+            .data(input_data);
+        let result = chain_state
+            .transactor
+            .lock()
+            .await
+            .send(
+                tx_request,
+                TransactionInfo::RegisterEpoch(chain_state.signer_address, next_epoch),
+            )
+            .await;
+        chain_state
+            .record_registration(RegistrationKind::Epoch, next_epoch, signature, &result)
+            .await;
+        match result {
+            Ok(result) => {
+                if result.success {
+                    info!("epoch {:?} registered", next_epoch);
+                    metrics::REGISTERED_EPOCH.set(next_epoch as f64);
+                    return Ok(());
                 }
+                bail!(anyhow!(format!("register epoch {:?} failed", next_epoch)));
+            }
+            Err(e) => {
+                bail!(anyhow!(e));
             }
         }
     }
     Ok(())
 }
 
-async fn check_new_quorums(chain_state: Arc<ChainState>, epoch: u64) -> Result<()> {
-    chain_state.fetch_quorum_if_missing(epoch).await?;
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ark_bn254::Fr;
+    use ark_ec::CurveGroup;
+    use contract_interface::{DARegistry, DASigners};
+    use ethers::providers::Provider;
     use std::str::FromStr;
 
+    #[test]
+    fn calldata_test() {
+        let (provider, _) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let da_registry = DARegistry::new(H160::repeat_byte(1), provider.clone());
+        let da_signers = DASigners::new(H160::repeat_byte(2), provider);
+
+        let signer = H160::repeat_byte(3);
+        let key = Fr::from(7u64);
+        let pk_g1 = (G1Affine::generator() * key).into_affine();
+        let pk_g2 = (G2Affine::generator() * key).into_affine();
+        let signature = (G1Affine::generator() * Fr::from(11u64)).into_affine();
+        let socket = "127.0.0.1:34000".to_string();
+
+        assert_eq!(
+            Some(register_signer_calldata(
+                signer,
+                socket.clone(),
+                pk_g1,
+                pk_g2,
+                signature
+            )),
+            da_registry
+                .register_signer(
+                    SignerDetail {
+                        signer,
+                        socket: socket.clone(),
+                        pk_g1: serialize_g1_point(pk_g1),
+                        pk_g2: serialize_g2_point(pk_g2),
+                    },
+                    serialize_g1_point(signature),
+                )
+                .calldata()
+        );
+        assert_eq!(
+            Some(register_next_epoch_calldata(signature)),
+            da_registry
+                .register_next_epoch(serialize_g1_point(signature))
+                .calldata()
+        );
+        assert_eq!(
+            Some(update_socket_calldata(socket.clone())),
+            da_signers.update_socket(socket).calldata()
+        );
+    }
+
     #[test]
     fn serialize_g1_point_test() {
         let point = G1Affine::new(
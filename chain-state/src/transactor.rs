@@ -1,30 +1,94 @@
+use std::{sync::Arc, time::Duration};
+
 use anyhow::{anyhow, bail, Result};
 
-use chain_utils::DefaultMiddleware;
-use ethers::types::H160;
+use chain_utils::gas::GasConfig;
+use ethers::types::{Bytes, H160, H256, U256};
 use ethers::{
-    providers::Middleware,
-    signers::{LocalWallet, Signer},
-    types::TransactionRequest,
+    providers::{Http, Middleware, PendingTransaction, Provider, RetryClient},
+    types::{
+        transaction::eip2718::TypedTransaction, BlockNumber, TransactionReceipt, TransactionRequest,
+    },
+};
+use key_signer::EthSigner;
+use serde::{Deserialize, Serialize};
+use storage::{
+    transaction_db::{InFlightTransaction, TransactionDB},
+    Storage,
 };
+use tokio::time::timeout;
 
-#[derive(Debug, Clone)]
+const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(120);
+// nodes only accept a replacement paying at least 10% more than the replaced transaction,
+// fees are raised by twice that so that fewer replacements are needed
+const FEE_BUMP_PERCENT: u64 = 20;
+// confirmation timeouts waited at the max fee per gas before giving up on a transaction
+const MAX_WAITS_AT_MAX_FEE: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionInfo {
     RegisterSigner(H160),
     RegisterEpoch(H160, u64),
     UpdateSocket(H160, String),
 }
 
-pub struct Transactor {
-    signer: LocalWallet,
-    client: DefaultMiddleware,
+#[derive(Debug, Clone)]
+pub struct TransactionResult {
+    pub success: bool,
+    pub tx_hash: H256,
+    pub block_number: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransactorConfig {
+    pub confirmation_timeout: Option<Duration>,
+    pub max_fee_per_gas: Option<U256>,
+    pub gas: GasConfig,
 }
 
-impl Transactor {
-    pub fn new(middleware: DefaultMiddleware) -> Result<Self> {
+pub struct Transactor<M = Provider<RetryClient<Http>>> {
+    signer: Arc<dyn EthSigner>,
+    client: Arc<M>,
+    chain_id: U256,
+    db: Arc<Storage>,
+    confirmation_timeout: Duration,
+    max_fee_per_gas: Option<U256>,
+    gas: GasConfig,
+    next_nonce: Option<U256>,
+    // transactions left unconfirmed by a previous run, ordered by nonce
+    in_flight: Vec<InFlightTransaction>,
+}
+
+impl<M: Middleware + 'static> Transactor<M> {
+    pub async fn new(
+        client: Arc<M>,
+        signer: Arc<dyn EthSigner>,
+        db: Arc<Storage>,
+        config: TransactorConfig,
+    ) -> Result<Self> {
+        let chain_id = client
+            .get_chainid()
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?;
+        let in_flight = db.get_in_flight_transactions(signer.address().0).await?;
+        if !in_flight.is_empty() {
+            info!(
+                "{:?} transactions left in flight by the previous run",
+                in_flight.len()
+            );
+        }
         Ok(Self {
-            signer: middleware.signer().clone(),
-            client: middleware,
+            signer,
+            client,
+            chain_id,
+            db,
+            confirmation_timeout: config
+                .confirmation_timeout
+                .unwrap_or(DEFAULT_CONFIRMATION_TIMEOUT),
+            max_fee_per_gas: config.max_fee_per_gas,
+            gas: config.gas,
+            next_nonce: None,
+            in_flight,
         })
     }
 
@@ -33,11 +97,16 @@ impl Transactor {
     }
 
     // return continue(true) or break(false)
-    fn handle_send_error(&self, e_str: &str, tx_info: TransactionInfo) -> bool {
+    fn handle_send_error(&self, e_str: &str, tx_info: &TransactionInfo) -> bool {
         if e_str.contains("max fee per gas less than block base fee") {
             info!("gas price too low, resending..");
             return true;
         }
+        if e_str.contains("replacement transaction underpriced") || e_str.contains("already known")
+        {
+            info!("transaction with the same nonce pending, resending with higher fees..");
+            return true;
+        }
         if e_str.contains("insufficient funds for transfer") {
             warn!(
                 "sender {:?} balance is insufficient.",
@@ -53,56 +122,483 @@ impl Transactor {
     }
 
     pub async fn send(
-        &self,
+        &mut self,
         tx_no_sender: TransactionRequest,
         tx_info: TransactionInfo,
-    ) -> Result<bool> {
-        let tx = tx_no_sender.clone().from(self.signer.address());
+    ) -> Result<TransactionResult> {
+        // finish what was left in flight first, so later nonces are not blocked
+        let data = tx_no_sender.data.clone().unwrap_or_default();
+        if let Some(result) = self.resume_in_flight(&tx_info, &data).await? {
+            return Ok(result);
+        }
+
+        let mut tx: TypedTransaction = tx_no_sender
+            .from(self.signer.address())
+            .chain_id(self.chain_id.as_u64())
+            .into();
         loop {
-            match self.client.send_transaction(tx.clone(), None).await {
-                Ok(pending_tx) => {
-                    let hash = pending_tx.tx_hash();
-                    info!(
-                        "new transaction sent with hash {:?}, tx_info: {:?}",
-                        hash, tx_info,
-                    );
-                    let mut status = 2;
-                    match pending_tx.await {
-                        Ok(maybe_receipt) => {
-                            if let Some(receipt) = maybe_receipt {
-                                if let Some(x) = receipt.status {
-                                    status = x.as_u32();
+            tx.set_nonce(self.next_nonce().await?);
+            let filled = match self.gas.apply(&*self.client, &mut tx).await {
+                Ok(()) => self
+                    .client
+                    .fill_transaction(&mut tx, None)
+                    .await
+                    .map_err(|e| anyhow!(e.to_string())),
+                Err(e) => Err(e),
+            };
+            match filled {
+                Ok(()) => break,
+                Err(e) => {
+                    // nothing was broadcast, resync the nonce from chain next time
+                    self.next_nonce = None;
+                    let e_str = e.to_string();
+                    if !self.handle_send_error(&e_str, &tx_info) {
+                        bail!(anyhow!(e_str));
+                    }
+                }
+            }
+        }
+        if let Err(e) = self.gas.check_tx_cost(&tx) {
+            self.next_nonce = None;
+            warn!("transaction {:?} skipped: {:?}", tx_info, e);
+            bail!(e);
+        }
+        self.confirm(&mut tx, &mut vec![], &tx_info).await
+    }
+
+    // returns the result of the in-flight transaction sent for `tx_info` with calldata `data`,
+    // if any
+    //
+    // a record is taken out of `in_flight` before it is resumed, so a failing one does not fail
+    // every later send, unless `confirm` puts it back as still pending
+    async fn resume_in_flight(
+        &mut self,
+        tx_info: &TransactionInfo,
+        data: &Bytes,
+    ) -> Result<Option<TransactionResult>> {
+        while !self.in_flight.is_empty() {
+            let record = self.in_flight.remove(0);
+            let record_info: TransactionInfo = serde_json::from_slice(&record.tx_info)?;
+            info!(
+                "resuming in-flight transaction with nonce {:?}, tx_info: {:?}",
+                record.nonce, record_info
+            );
+            let mut tx: TypedTransaction = serde_json::from_slice(&record.tx)?;
+            let mut tx_hashes = record.tx_hashes.iter().map(|x| H256(*x)).collect();
+            let result = self.confirm(&mut tx, &mut tx_hashes, &record_info).await?;
+            if record_info == *tx_info && tx.data().cloned().unwrap_or_default() == *data {
+                return Ok(Some(result));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn next_nonce(&mut self) -> Result<U256> {
+        let nonce = match self.next_nonce {
+            Some(x) => x,
+            None => self
+                .client
+                .get_transaction_count(self.signer.address(), Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(|e| anyhow!(e.to_string()))?,
+        };
+        self.next_nonce = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    // broadcast the transaction and wait for it, replacing it with higher fees when it is
+    // not confirmed in time
+    async fn confirm(
+        &mut self,
+        tx: &mut TypedTransaction,
+        tx_hashes: &mut Vec<H256>,
+        tx_info: &TransactionInfo,
+    ) -> Result<TransactionResult> {
+        let nonce = tx
+            .nonce()
+            .copied()
+            .ok_or_else(|| anyhow!("transaction nonce is not set"))?
+            .as_u64();
+        let mut resend = true;
+        let mut waits_at_max_fee = 0;
+        loop {
+            // an earlier attempt may have been mined while a replacement was pending
+            if let Some(receipt) = self.find_receipt(tx_hashes).await? {
+                return self.finish(nonce, receipt).await;
+            }
+            if resend {
+                match self.sign_and_send(tx).await {
+                    Ok(hash) => {
+                        info!(
+                            "new transaction sent with hash {:?}, nonce {:?}, tx_info: {:?}",
+                            hash, nonce, tx_info,
+                        );
+                        tx_hashes.push(hash);
+                        self.persist(nonce, tx, tx_hashes, tx_info).await?;
+                    }
+                    Err(e) => {
+                        let e_str = e.to_string();
+                        if !self.handle_send_error(&e_str, tx_info) {
+                            if e_str.contains("nonce too low") && !tx_hashes.is_empty() {
+                                if let Some(receipt) = self.find_receipt(tx_hashes).await? {
+                                    return self.finish(nonce, receipt).await;
                                 }
+                                // the nonce is taken by a transaction sent elsewhere
+                                self.db
+                                    .delete_in_flight_transaction(self.signer.address().0, nonce)
+                                    .await?;
                             }
+                            self.next_nonce = None;
+                            bail!(anyhow!(e_str));
                         }
-                        Err(e) => {
-                            info!("transaction {:?} error: {:?}", hash, e);
-                        }
-                    }
-                    match status {
-                        0 => {
-                            info!("transaction {:?} failed.", hash);
-                            return Ok(false);
-                        }
-                        1 => {
-                            info!("transaction {:?} success.", hash)
+                        if !bump_fees(tx, self.fee_ceiling(tx)) {
+                            bail!(anyhow!("{}, max fee per gas reached", e_str));
                         }
-                        2 => {
-                            info!("transaction {:?} confirmed, status unknown.", hash)
-                        }
-                        _ => {}
-                    }
-                    return Ok(true);
-                }
-                Err(e) => {
-                    let e_str = e.to_string();
-                    if self.handle_send_error(&e_str, tx_info.clone()) {
                         continue;
-                    } else {
-                        bail!(anyhow!(e_str));
                     }
                 }
             }
+
+            let hash = *tx_hashes
+                .last()
+                .ok_or_else(|| anyhow!("no transaction sent"))?;
+            let pending_tx = PendingTransaction::new(hash, self.client.provider());
+            let dropped = match timeout(self.confirmation_timeout, pending_tx).await {
+                Ok(Ok(Some(receipt))) => return self.finish(nonce, receipt).await,
+                Ok(Ok(None)) => {
+                    info!("transaction {:?} dropped from mempool.", hash);
+                    true
+                }
+                Ok(Err(e)) => {
+                    info!("transaction {:?} error: {:?}", hash, e);
+                    false
+                }
+                Err(_) => {
+                    info!(
+                        "transaction {:?} not confirmed in {:?}.",
+                        hash, self.confirmation_timeout
+                    );
+                    false
+                }
+            };
+            resend = bump_fees(tx, self.fee_ceiling(tx));
+            if resend {
+                info!("replacing transaction {:?} with higher fees", hash);
+            } else if dropped {
+                resend = true;
+            } else {
+                waits_at_max_fee += 1;
+                if waits_at_max_fee >= MAX_WAITS_AT_MAX_FEE {
+                    // still pending with this nonce, the next send waits for it first
+                    self.in_flight
+                        .insert(0, self.in_flight_record(nonce, tx, tx_hashes, tx_info)?);
+                    bail!(
+                        "transaction {:?} not confirmed at max fee per gas, left pending",
+                        hash
+                    );
+                }
+                warn!(
+                    "transaction {:?} reached max fee per gas, keep waiting",
+                    hash
+                );
+            }
+        }
+    }
+
+    async fn sign_and_send(&self, tx: &TypedTransaction) -> Result<H256> {
+        let signature = self.signer.sign_transaction(tx).await?;
+        Ok(self
+            .client
+            .send_raw_transaction(tx.rlp_signed(&signature))
+            .await
+            .map_err(|e| anyhow!(e.to_string()))?
+            .tx_hash())
+    }
+
+    async fn find_receipt(&self, tx_hashes: &[H256]) -> Result<Option<TransactionReceipt>> {
+        for hash in tx_hashes {
+            if let Some(receipt) = self
+                .client
+                .get_transaction_receipt(*hash)
+                .await
+                .map_err(|e| anyhow!(e.to_string()))?
+            {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn finish(
+        &mut self,
+        nonce: u64,
+        receipt: TransactionReceipt,
+    ) -> Result<TransactionResult> {
+        self.db
+            .delete_in_flight_transaction(self.signer.address().0, nonce)
+            .await?;
+        let next_nonce = U256::from(nonce + 1);
+        if self.next_nonce.unwrap_or_default() < next_nonce {
+            self.next_nonce = Some(next_nonce);
+        }
+
+        let hash = receipt.transaction_hash;
+        let block_number = receipt.block_number.map(|x| x.as_u64());
+        match receipt.status.map(|x| x.as_u32()) {
+            Some(0) => {
+                info!("transaction {:?} failed.", hash);
+                return Ok(TransactionResult {
+                    success: false,
+                    tx_hash: hash,
+                    block_number,
+                });
+            }
+            Some(1) => {
+                info!("transaction {:?} success.", hash)
+            }
+            None => {
+                info!("transaction {:?} confirmed, status unknown.", hash)
+            }
+            _ => {}
+        }
+        Ok(TransactionResult {
+            success: true,
+            tx_hash: hash,
+            block_number,
+        })
+    }
+
+    async fn persist(
+        &self,
+        nonce: u64,
+        tx: &TypedTransaction,
+        tx_hashes: &[H256],
+        tx_info: &TransactionInfo,
+    ) -> Result<()> {
+        self.db
+            .put_in_flight_transaction(self.in_flight_record(nonce, tx, tx_hashes, tx_info)?)
+            .await
+    }
+
+    fn in_flight_record(
+        &self,
+        nonce: u64,
+        tx: &TypedTransaction,
+        tx_hashes: &[H256],
+        tx_info: &TransactionInfo,
+    ) -> Result<InFlightTransaction> {
+        Ok(InFlightTransaction {
+            sender: self.signer.address().0,
+            nonce,
+            tx_info: serde_json::to_vec(tx_info)?,
+            tx: serde_json::to_vec(tx)?,
+            tx_hashes: tx_hashes.iter().map(|x| x.0).collect(),
+        })
+    }
+
+    fn fee_ceiling(&self, tx: &TypedTransaction) -> Option<U256> {
+        match (self.max_fee_per_gas, self.gas.fee_ceiling(tx)) {
+            (Some(x), Some(y)) => Some(x.min(y)),
+            (x, y) => x.or(y),
+        }
+    }
+}
+
+// return false if the fees cannot be raised any more
+fn bump_fees(tx: &mut TypedTransaction, ceiling: Option<U256>) -> bool {
+    let bump = |x: U256| x * (100 + FEE_BUMP_PERCENT) / 100;
+    let cap = |old: U256, new: U256| match ceiling {
+        Some(ceiling) if old >= ceiling => None,
+        Some(ceiling) => Some(new.min(ceiling)),
+        None => Some(new),
+    };
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            let (max_fee, priority_fee) =
+                match (inner.max_fee_per_gas, inner.max_priority_fee_per_gas) {
+                    (Some(x), Some(y)) => (x, y),
+                    _ => return false,
+                };
+            match cap(max_fee, bump(max_fee)) {
+                Some(new_max_fee) => {
+                    inner.max_fee_per_gas = Some(new_max_fee);
+                    inner.max_priority_fee_per_gas = Some(bump(priority_fee).min(new_max_fee));
+                    true
+                }
+                None => false,
+            }
         }
+        _ => {
+            let gas_price = match tx.gas_price() {
+                Some(x) => x,
+                None => return false,
+            };
+            match cap(gas_price, bump(gas_price)) {
+                Some(new_gas_price) => {
+                    tx.set_gas_price(new_gas_price);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{providers::MockProvider, signers::LocalWallet, types::Eip1559TransactionRequest};
+
+    #[test]
+    fn bump_fees_test() {
+        let mut legacy: TypedTransaction = TransactionRequest::new().gas_price(100).into();
+        assert!(bump_fees(&mut legacy, None));
+        assert_eq!(legacy.gas_price(), Some(120.into()));
+        assert!(bump_fees(&mut legacy, Some(130.into())));
+        assert_eq!(legacy.gas_price(), Some(130.into()));
+        assert!(!bump_fees(&mut legacy, Some(130.into())));
+
+        let mut eip1559: TypedTransaction = Eip1559TransactionRequest::new()
+            .max_fee_per_gas(100)
+            .max_priority_fee_per_gas(95)
+            .into();
+        assert!(bump_fees(&mut eip1559, Some(110.into())));
+        match eip1559 {
+            TypedTransaction::Eip1559(inner) => {
+                assert_eq!(inner.max_fee_per_gas, Some(110.into()));
+                // the priority fee is kept within the max fee
+                assert_eq!(inner.max_priority_fee_per_gas, Some(110.into()));
+            }
+            _ => unreachable!(),
+        }
+
+        let mut unfilled: TypedTransaction = TransactionRequest::new().into();
+        assert!(!bump_fees(&mut unfilled, None));
+    }
+
+    fn transactor() -> (Transactor<Provider<MockProvider>>, MockProvider) {
+        let (provider, mock) = Provider::mocked();
+        let transactor = Transactor {
+            signer: Arc::new(LocalWallet::from_bytes(&[1; 32]).unwrap()),
+            client: Arc::new(provider),
+            chain_id: U256::one(),
+            db: Arc::new(Storage::in_memory()),
+            confirmation_timeout: DEFAULT_CONFIRMATION_TIMEOUT,
+            max_fee_per_gas: None,
+            gas: GasConfig::default(),
+            next_nonce: None,
+            in_flight: vec![],
+        };
+        (transactor, mock)
+    }
+
+    async fn persist_in_flight(
+        transactor: &mut Transactor<Provider<MockProvider>>,
+        tx: Vec<u8>,
+        tx_info: &TransactionInfo,
+    ) {
+        let sender = transactor.signer_address().0;
+        transactor
+            .db
+            .put_in_flight_transaction(InFlightTransaction {
+                sender,
+                nonce: 5,
+                tx_info: serde_json::to_vec(tx_info).unwrap(),
+                tx,
+                tx_hashes: vec![[7; 32]],
+            })
+            .await
+            .unwrap();
+        transactor.in_flight = transactor
+            .db
+            .get_in_flight_transactions(sender)
+            .await
+            .unwrap();
+    }
+
+    fn receipt() -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: H256([7; 32]),
+            block_number: Some(10.into()),
+            status: Some(1.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_in_flight_test() {
+        let (mut transactor, mock) = transactor();
+        mock.push::<TransactionReceipt, _>(receipt()).unwrap();
+        let tx_info = TransactionInfo::RegisterSigner(H160::zero());
+        let tx: TypedTransaction = TransactionRequest::new().nonce(5).data(vec![1, 2]).into();
+        persist_in_flight(&mut transactor, serde_json::to_vec(&tx).unwrap(), &tx_info).await;
+
+        // the transaction sent by the previous run is returned instead of sending another one
+        let result = transactor
+            .send(TransactionRequest::new().data(vec![1, 2]), tx_info)
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.tx_hash, H256([7; 32]));
+        assert_eq!(result.block_number, Some(10));
+        assert!(transactor.in_flight.is_empty());
+        assert_eq!(transactor.next_nonce, Some(6.into()));
+        let sender = transactor.signer_address().0;
+        assert!(transactor
+            .db
+            .get_in_flight_transactions(sender)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn resume_other_in_flight_test() {
+        let tx: TypedTransaction = TransactionRequest::new().nonce(5).data(vec![1, 2]).into();
+        let tx_info = TransactionInfo::UpdateSocket(H160::zero(), "socket".to_string());
+        for (other_info, other_data) in [
+            (
+                TransactionInfo::UpdateSocket(H160::zero(), "other".to_string()),
+                vec![1, 2],
+            ),
+            (tx_info.clone(), vec![3]),
+        ] {
+            let (mut transactor, mock) = transactor();
+            mock.push::<TransactionReceipt, _>(receipt()).unwrap();
+            persist_in_flight(&mut transactor, serde_json::to_vec(&tx).unwrap(), &tx_info).await;
+
+            // the record is confirmed, but not taken as the result of a different transaction
+            assert!(transactor
+                .resume_in_flight(&other_info, &other_data.into())
+                .await
+                .unwrap()
+                .is_none());
+            assert!(transactor.in_flight.is_empty());
+            assert_eq!(transactor.next_nonce, Some(6.into()));
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_failed_in_flight_test() {
+        let (mut transactor, _) = transactor();
+        let tx_info = TransactionInfo::RegisterEpoch(H160::zero(), 1);
+        persist_in_flight(&mut transactor, b"invalid".to_vec(), &tx_info).await;
+
+        assert!(transactor
+            .resume_in_flight(&tx_info, &Bytes::new())
+            .await
+            .is_err());
+        // later sends are not failed by the same record, which is kept for the next run
+        assert!(transactor.in_flight.is_empty());
+        let sender = transactor.signer_address().0;
+        assert_eq!(
+            transactor
+                .db
+                .get_in_flight_transactions(sender)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
     }
 }
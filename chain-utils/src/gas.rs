@@ -0,0 +1,248 @@
+use anyhow::{anyhow, bail, Result};
+use ethers::{
+    providers::Middleware,
+    types::{
+        transaction::eip2718::TypedTransaction, Eip1559TransactionRequest, TransactionRequest, U256,
+    },
+};
+
+#[derive(Debug, Clone, Default)]
+pub enum GasStrategy {
+    /// Legacy transaction priced by `eth_gasPrice`.
+    #[default]
+    Legacy,
+    /// Legacy transaction with a fixed gas price in wei.
+    Fixed(U256),
+    /// Legacy transaction priced by `eth_gasPrice` scaled by a percentage.
+    OracleMultiplier(u64),
+    /// EIP-1559 transaction with fees estimated by the node.
+    Eip1559 {
+        max_priority_fee_per_gas: Option<U256>,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GasConfig {
+    pub strategy: GasStrategy,
+    /// Transactions which may spend more than this in wei on gas are refused.
+    pub max_tx_cost: Option<U256>,
+}
+
+impl GasConfig {
+    /// Sets the transaction type and fees, estimating the gas limit if missing.
+    pub async fn apply<M: Middleware>(&self, client: &M, tx: &mut TypedTransaction) -> Result<()> {
+        if tx.gas().is_none() {
+            let gas = client
+                .estimate_gas(tx, None)
+                .await
+                .map_err(|e| anyhow!(e.to_string()))?;
+            tx.set_gas(gas);
+        }
+        match &self.strategy {
+            GasStrategy::Eip1559 {
+                max_priority_fee_per_gas,
+            } => {
+                let (max_fee, mut priority_fee) = client
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(|e| anyhow!(e.to_string()))?;
+                if let Some(cap) = max_priority_fee_per_gas {
+                    priority_fee = priority_fee.min(*cap);
+                }
+                let inner: Eip1559TransactionRequest = tx.clone().into();
+                *tx = inner
+                    .max_fee_per_gas(max_fee)
+                    .max_priority_fee_per_gas(priority_fee)
+                    .into();
+            }
+            strategy => {
+                let gas_price = match strategy {
+                    GasStrategy::Fixed(gas_price) => *gas_price,
+                    GasStrategy::OracleMultiplier(percent) => {
+                        self.oracle_gas_price(client).await? * *percent / 100
+                    }
+                    _ => self.oracle_gas_price(client).await?,
+                };
+                let inner: TransactionRequest = tx.clone().into();
+                *tx = inner.gas_price(gas_price).into();
+            }
+        }
+        Ok(())
+    }
+
+    async fn oracle_gas_price<M: Middleware>(&self, client: &M) -> Result<U256> {
+        client
+            .get_gas_price()
+            .await
+            .map_err(|e| anyhow!(e.to_string()))
+    }
+
+    /// Highest fee per gas the transaction may pay without exceeding `max_tx_cost`.
+    pub fn fee_ceiling(&self, tx: &TypedTransaction) -> Option<U256> {
+        match (self.max_tx_cost, tx.gas()) {
+            (Some(max_cost), Some(gas)) if !gas.is_zero() => Some(max_cost / gas),
+            _ => None,
+        }
+    }
+
+    pub fn check_tx_cost(&self, tx: &TypedTransaction) -> Result<()> {
+        let max_cost = match self.max_tx_cost {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let cost = tx.gas().copied().unwrap_or_default() * fee_per_gas(tx).unwrap_or_default();
+        if cost > max_cost {
+            bail!(
+                "transaction may cost {:?} wei on gas, above the cap of {:?} wei",
+                cost,
+                max_cost
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Gas price of legacy transactions, or max fee per gas of EIP-1559 transactions.
+pub fn fee_per_gas(tx: &TypedTransaction) -> Option<U256> {
+    match tx {
+        TypedTransaction::Eip1559(inner) => inner.max_fee_per_gas,
+        _ => tx.gas_price(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        providers::{MockProvider, Provider},
+        types::{Block, FeeHistory, H256},
+    };
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn legacy_tx(gas: u64) -> TypedTransaction {
+        TransactionRequest::new().gas(gas).into()
+    }
+
+    async fn apply(
+        strategy: GasStrategy,
+        tx: &mut TypedTransaction,
+        mock: impl FnOnce(&MockProvider),
+    ) {
+        let (provider, mock_provider) = Provider::mocked();
+        // responses are popped from the back
+        mock(&mock_provider);
+        GasConfig {
+            strategy,
+            max_tx_cost: None,
+        }
+        .apply(&provider, tx)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn legacy_strategy_test() {
+        let mut tx = legacy_tx(21000);
+        apply(GasStrategy::Legacy, &mut tx, |m| {
+            m.push(U256::from(7 * GWEI)).unwrap()
+        })
+        .await;
+        assert_eq!(tx.gas_price(), Some(U256::from(7 * GWEI)));
+        assert_eq!(tx.gas(), Some(&U256::from(21000)));
+    }
+
+    #[tokio::test]
+    async fn fixed_strategy_test() {
+        let mut tx = legacy_tx(21000);
+        apply(GasStrategy::Fixed(U256::from(5 * GWEI)), &mut tx, |_| {}).await;
+        assert_eq!(tx.gas_price(), Some(U256::from(5 * GWEI)));
+    }
+
+    #[tokio::test]
+    async fn oracle_multiplier_strategy_test() {
+        let mut tx: TypedTransaction = TransactionRequest::new().into();
+        apply(GasStrategy::OracleMultiplier(150), &mut tx, |m| {
+            m.push(U256::from(10 * GWEI)).unwrap();
+            // the gas limit is estimated first
+            m.push(U256::from(50000)).unwrap();
+        })
+        .await;
+        assert_eq!(tx.gas_price(), Some(U256::from(15 * GWEI)));
+        assert_eq!(tx.gas(), Some(&U256::from(50000)));
+    }
+
+    #[tokio::test]
+    async fn eip1559_strategy_test() {
+        let mut tx = legacy_tx(21000);
+        apply(
+            GasStrategy::Eip1559 {
+                max_priority_fee_per_gas: Some(U256::from(GWEI)),
+            },
+            &mut tx,
+            |m| {
+                m.push(FeeHistory {
+                    base_fee_per_gas: vec![],
+                    gas_used_ratio: vec![],
+                    oldest_block: U256::zero(),
+                    reward: vec![],
+                })
+                .unwrap();
+                m.push(Block::<H256> {
+                    base_fee_per_gas: Some(U256::from(GWEI)),
+                    ..Default::default()
+                })
+                .unwrap();
+            },
+        )
+        .await;
+        match tx {
+            TypedTransaction::Eip1559(inner) => {
+                // the estimated priority fee of 3 gwei is capped, and the max fee covers a
+                // doubled base fee on top of it
+                assert_eq!(inner.max_priority_fee_per_gas, Some(U256::from(GWEI)));
+                assert_eq!(inner.max_fee_per_gas, Some(U256::from(5 * GWEI)));
+                assert_eq!(inner.gas, Some(U256::from(21000)));
+            }
+            _ => panic!("not an EIP-1559 transaction"),
+        }
+    }
+
+    #[test]
+    fn fee_ceiling_test() {
+        let config = GasConfig {
+            strategy: GasStrategy::Legacy,
+            max_tx_cost: Some(U256::from(21000 * GWEI)),
+        };
+        assert_eq!(
+            config.fee_ceiling(&legacy_tx(21000)),
+            Some(U256::from(GWEI))
+        );
+        assert_eq!(config.fee_ceiling(&legacy_tx(0)), None);
+        assert_eq!(GasConfig::default().fee_ceiling(&legacy_tx(21000)), None);
+    }
+
+    #[test]
+    fn check_tx_cost_test() {
+        let config = GasConfig {
+            strategy: GasStrategy::Legacy,
+            max_tx_cost: Some(U256::from(21000 * GWEI)),
+        };
+        let tx = |gas_price: u64| -> TypedTransaction {
+            TransactionRequest::new()
+                .gas(21000)
+                .gas_price(gas_price)
+                .into()
+        };
+        assert!(config.check_tx_cost(&tx(GWEI)).is_ok());
+        // the spend cap refuses a transaction which may cost more
+        assert!(config.check_tx_cost(&tx(GWEI + 1)).is_err());
+        let eip1559: TypedTransaction = Eip1559TransactionRequest::new()
+            .gas(21000)
+            .max_fee_per_gas(2 * GWEI)
+            .max_priority_fee_per_gas(1)
+            .into();
+        assert!(config.check_tx_cost(&eip1559).is_err());
+        assert!(GasConfig::default().check_tx_cost(&tx(100 * GWEI)).is_ok());
+    }
+}
@@ -11,20 +11,26 @@ use ethers::{
 };
 use reqwest::Url;
 
+pub mod gas;
+
 pub type DefaultMiddleware = Arc<DefaultMiddlewareInner>;
 pub type DefaultMiddlewareInner = SignerMiddleware<Provider<RetryClient<Http>>, LocalWallet>;
 
 pub const DA_SIGNER_ADDRESS: &str = "0x0000000000000000000000000000000000001000";
 pub const DA_REGISTRY_ADDRESS: &str = "0x20f30b2584f3096ea0d6c18c3b5cacc0585e12fc";
 
-pub async fn make_provider(eth_rpc_url: &str, eth_private_key: &H256) -> Result<DefaultMiddleware> {
+pub fn make_rpc_provider(eth_rpc_url: &str) -> Result<Provider<RetryClient<Http>>> {
     let client = reqwest::ClientBuilder::default()
         .timeout(Duration::from_secs(60))
         .build()?;
     let http_client = Http::new_with_client(Url::parse(eth_rpc_url)?, client);
-    let provider = Provider::new(
+    Ok(Provider::new(
         RetryClientBuilder::default().build(http_client, Box::new(HttpRateLimitRetryPolicy)),
-    );
+    ))
+}
+
+pub async fn make_provider(eth_rpc_url: &str, eth_private_key: &H256) -> Result<DefaultMiddleware> {
+    let provider = make_rpc_provider(eth_rpc_url)?;
 
     let local_wallet = LocalWallet::from_bytes(&eth_private_key[..])
         .map_err(|e| anyhow!("Invalid validator private key: {:?}", e))?;
@@ -1,5 +1 @@
-use zg_encoder::constants::{BLOB_COL_N, BLOB_UNIT};
-
-pub const NUM_SUBLINES: usize = 32;
-pub const LINE_BYTES: usize = BLOB_COL_N * BLOB_UNIT;
-pub const SUBLINE_BYTES: usize = LINE_BYTES / NUM_SUBLINES;
+pub use utils::line::{LINE_BYTES, NUM_SUBLINES, SUBLINE_BYTES};
@@ -129,3 +129,52 @@ impl LineMetadata {
         (answer, Some(last_epoch))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use storage::Storage;
+
+    #[tokio::test]
+    async fn fetch_epoch_test() {
+        let db = Storage::in_memory();
+        db.put_slice(3, 1, [3; 32], vec![]).await.unwrap();
+        db.put_slice(4, 0, [4; 32], vec![]).await.unwrap();
+
+        let mut metadata = LineMetadata::default();
+        metadata.set_epoch_range(2, 4);
+        assert!(metadata.needs_fetch());
+        metadata
+            .fetch_epoch(&db, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(!metadata.needs_fetch());
+        // epochs without slices are fetched empty
+        assert_eq!(
+            metadata.data.keys().copied().collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert!(metadata.data[&2].is_empty());
+        assert_eq!(
+            metadata.data[&3].iter().collect::<Vec<_>>(),
+            vec![&BlobInfo {
+                quorum_id: 1,
+                storage_root: [3; 32],
+                indicies: vec![],
+            }]
+        );
+
+        // epochs kept in the range are not fetched again
+        db.put_slice(4, 1, [5; 32], vec![]).await.unwrap();
+        metadata.set_epoch_range(3, 5);
+        metadata
+            .fetch_epoch(&db, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata.data.keys().copied().collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        assert_eq!(metadata.data[&4].len(), 1);
+    }
+}
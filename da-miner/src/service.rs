@@ -1,11 +1,11 @@
 use std::sync::Arc;
 
-use chain_utils::DefaultMiddleware;
+use chain_utils::{gas::GasConfig, DefaultMiddleware};
 use contract_interface::da_sample::SampleResponse;
 use ethers::types::Address;
 use storage::Storage;
 use task_executor::TaskExecutor;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc};
 
 use crate::{
     line_candidate::LineCandidate, mock_data::store_mock_data, stage1::DasStage1Miner,
@@ -20,13 +20,14 @@ impl DasMineService {
         provider: DefaultMiddleware,
         da_address: Address,
         das_test: bool,
-        store: Arc<RwLock<Storage>>,
+        store: Arc<Storage>,
+        gas_config: GasConfig,
     ) -> Result<(), String> {
         info_span!("start_mine_service");
 
         if das_test {
             info!("Start store mock da data");
-            store_mock_data("./params", &*store.read().await).await;
+            store_mock_data("./params", &*store).await;
         }
 
         let (on_chain_sender, on_chain_receiver) = broadcast::channel(1024);
@@ -63,6 +64,7 @@ impl DasMineService {
             on_chain_receiver.resubscribe(),
             submission_receiver,
             da_address,
+            gas_config,
         );
 
         Ok(())
@@ -3,7 +3,7 @@ use std::{sync::Arc, time::Duration};
 use ethers::types::U256;
 use storage::Storage;
 use task_executor::TaskExecutor;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc};
 use utils::metrics;
 
 use crate::{
@@ -13,7 +13,7 @@ use crate::{
 };
 
 pub struct DasStage1Miner {
-    db: Arc<RwLock<Storage>>,
+    db: Arc<Storage>,
     on_chain_receiver: broadcast::Receiver<OnChainChangeMessage>,
     first_stage_sender: mpsc::UnboundedSender<Vec<LineCandidate>>,
 
@@ -23,7 +23,7 @@ pub struct DasStage1Miner {
 impl DasStage1Miner {
     pub fn spawn(
         executor: TaskExecutor,
-        db: Arc<RwLock<Storage>>,
+        db: Arc<Storage>,
         on_chain_receiver: broadcast::Receiver<OnChainChangeMessage>,
         first_stage_sender: mpsc::UnboundedSender<Vec<LineCandidate>>,
     ) {
@@ -81,9 +81,10 @@ impl DasStage1Miner {
                     }
                 }
 
-                // FIXME: the db load task may suffer a starvation because of line tasks.
-                db = self.db.read(), if self.lines.needs_fetch() => {
-                    if let Err(error) = self.lines.fetch_epoch(&*db, Duration::from_millis(100)).await {
+                // polled before mining by the biased select, in rounds of 100ms so that on-chain
+                // messages are not held up by loading many epochs
+                _ = async {}, if self.lines.needs_fetch() => {
+                    if let Err(error) = self.lines.fetch_epoch(&*self.db, Duration::from_millis(100)).await {
                         warn!(?error, "DB error when fetching epochs");
                     }
                 }
@@ -6,13 +6,12 @@ use storage::slice_db::SliceDB;
 use storage::Storage;
 use task_executor::TaskExecutor;
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
 use utils::metrics;
 
 use crate::line_candidate::LineCandidate;
 
 pub struct DasStage2Miner {
-    db: Arc<RwLock<Storage>>,
+    db: Arc<Storage>,
     first_stage_receiver: mpsc::UnboundedReceiver<Vec<LineCandidate>>,
     submission_sender: mpsc::UnboundedSender<SampleResponse>,
 }
@@ -20,7 +19,7 @@ pub struct DasStage2Miner {
 impl DasStage2Miner {
     pub fn spawn(
         executor: TaskExecutor,
-        db: Arc<RwLock<Storage>>,
+        db: Arc<Storage>,
         first_stage_receiver: mpsc::UnboundedReceiver<Vec<LineCandidate>>,
         submission_sender: mpsc::UnboundedSender<SampleResponse>,
     ) {
@@ -58,8 +57,8 @@ impl DasStage2Miner {
                     }
                 },
 
-                db = self.db.read(), if !line_candidates.is_empty() && miner_enabled => {
-                    if let Err(e) = self.mine(&*db, &mut line_candidates).await {
+                _ = async {}, if !line_candidates.is_empty() && miner_enabled => {
+                    if let Err(e) = self.mine(&*self.db, &mut line_candidates).await {
                         warn!(error = e, "Unexpected error, mine service stopped");
                         miner_enabled = false;
                         self.first_stage_receiver.close();
@@ -1,4 +1,4 @@
-use chain_utils::{DefaultMiddleware, DefaultMiddlewareInner};
+use chain_utils::{gas::GasConfig, DefaultMiddleware, DefaultMiddlewareInner};
 use contract_interface::{da_sample::SampleResponse, DASample};
 use ethers::{abi::Address, contract::ContractCall, providers::PendingTransaction, utils::hex};
 use task_executor::TaskExecutor;
@@ -8,6 +8,8 @@ use crate::watcher::OnChainChangeMessage;
 
 pub struct DasSubmitter {
     da_contract: DASample<DefaultMiddlewareInner>,
+    provider: DefaultMiddleware,
+    gas_config: GasConfig,
     on_chain_receiver: broadcast::Receiver<OnChainChangeMessage>,
     submission_receiver: mpsc::UnboundedReceiver<SampleResponse>,
 }
@@ -19,10 +21,13 @@ impl DasSubmitter {
         on_chain_receiver: broadcast::Receiver<OnChainChangeMessage>,
         submission_receiver: mpsc::UnboundedReceiver<SampleResponse>,
         da_address: Address,
+        gas_config: GasConfig,
     ) {
         let da_contract = DASample::new(da_address, provider.clone());
         let submitter = Self {
             da_contract,
+            provider,
+            gas_config,
             submission_receiver,
             on_chain_receiver,
         };
@@ -105,12 +110,21 @@ impl DasSubmitter {
             return Err(());
         }
 
-        let submission_call: ContractCall<_, _> =
-            self.da_contract.submit_sampling_response(response).legacy();
+        let mut submission_call: ContractCall<_, _> =
+            self.da_contract.submit_sampling_response(response);
+        self.gas_config
+            .apply(&*self.provider, &mut submission_call.tx)
+            .await
+            .map_err(|e| {
+                warn!(error = ?e, "Fail to price sample response transaction");
+            })?;
         debug!(transaction = ?submission_call.tx, "Construct transaction");
 
-        let estimate_gas = submission_call.estimate_gas().await;
-        debug!(result = ?estimate_gas, "Estimate gas");
+        self.gas_config
+            .check_tx_cost(&submission_call.tx)
+            .map_err(|e| {
+                warn!(error = ?e, "Give up submission above the spend cap");
+            })?;
 
         let pending_transaction: PendingTransaction<'_, _> =
             submission_call.send().await.map_err(|e| {
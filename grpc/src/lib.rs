@@ -4,21 +4,21 @@ extern crate tracing;
 mod service;
 
 use crate::service::signer::signer_server::SignerServer;
-use ark_bn254::Fr;
 use chain_state::ChainState;
+use key_signer::{BlsKeyRing, BlsSigner};
 pub use service::signer;
 use service::SignerService;
 use std::{net::SocketAddr, sync::Arc};
 use storage::Storage;
-use tokio::sync::RwLock;
 use tonic::transport::Server;
 
 const MESSAGE_SIZE_LIMIT: usize = 1024 * 1024 * 1024; // 1G
 
 pub async fn run_server(
-    db: Arc<RwLock<Storage>>,
+    db: Arc<Storage>,
     chain_state: Arc<ChainState>,
-    signer_bls_private_key: Fr,
+    bls_signer: Arc<dyn BlsSigner>,
+    bls_keys: Arc<BlsKeyRing>,
     addr: SocketAddr,
     encoder_params_dir: String,
     max_ongoing_sign_request: Option<u64>,
@@ -26,7 +26,8 @@ pub async fn run_server(
     let signer_service = SignerService::new(
         db,
         chain_state,
-        signer_bls_private_key,
+        bls_signer,
+        bls_keys,
         encoder_params_dir,
         max_ongoing_sign_request,
     );
@@ -11,13 +11,20 @@ use chain_state::ChainState;
 use ethers::abi::{self, Token};
 use ethers::types::{Res, U256};
 use ethers::utils::keccak256;
+use key_signer::{BlobSignRequest, BlsKeyRing, BlsSigner, SlashingProtectionError};
 use prost::Message;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use signer::{BatchRetrieveReply, BatchRetrieveRequest, Empty, Slices};
+use signer::{
+    BatchRetrieveReply, BatchRetrieveRequest, CheckSignatureReply, CheckSignatureRequest, Empty,
+    RegistrationHistoryReply, RegistrationHistoryRequest, RetrieveRequest, Slices,
+};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use storage::blob_status_db::{BlobStatus, BlobStatusDB};
 use storage::quorum_db::{AssignedSlices, QuorumDB};
+use storage::registration_db::{
+    RegistrationDB, RegistrationKind, RegistrationOutcome, RegistrationRecord,
+};
 use storage::slice_db::SliceDB;
 use storage::Storage;
 use tokio::sync::RwLock;
@@ -33,11 +40,14 @@ pub mod signer {
 }
 
 const DEFAULT_MAX_ONGOING_SIGN_REQUEST: u64 = 10;
+const DEFAULT_REGISTRATION_HISTORY_LIMIT: usize = 100;
 
 pub struct SignerService {
-    db: Arc<RwLock<Storage>>,
+    db: Arc<Storage>,
     chain_state: Arc<ChainState>,
-    signer_bls_private_key: Fr,
+    bls_signer: Arc<dyn BlsSigner>,
+    // the local keys by epoch, which signatures are checked against
+    bls_keys: Arc<BlsKeyRing>,
     encoder_params: ZgSignerParams,
     max_ongoing_sign_request: u64,
     ongoing_sign_request_cnt: Arc<RwLock<u64>>,
@@ -45,16 +55,18 @@ pub struct SignerService {
 
 impl SignerService {
     pub fn new(
-        db: Arc<RwLock<Storage>>,
+        db: Arc<Storage>,
         chain_state: Arc<ChainState>,
-        signer_bls_private_key: Fr,
+        bls_signer: Arc<dyn BlsSigner>,
+        bls_keys: Arc<BlsKeyRing>,
         params_dir: String,
         max_ongoing_sign_request: Option<u64>,
     ) -> Self {
         Self {
             db,
             chain_state,
-            signer_bls_private_key,
+            bls_signer,
+            bls_keys,
             encoder_params: ZgSignerParams::from_dir_mont(params_dir),
             max_ongoing_sign_request: max_ongoing_sign_request
                 .unwrap_or(DEFAULT_MAX_ONGOING_SIGN_REQUEST),
@@ -80,6 +92,9 @@ impl SignerService {
         &self,
         request: Request<BatchSignRequest>,
     ) -> Result<Response<BatchSignReply>, Status> {
+        if let Some(e) = self.chain_state.key_mismatch().await {
+            return Err(Status::new(Code::FailedPrecondition, e));
+        }
         let remote_addr = request.remote_addr();
         let request_content = request.into_inner();
         metrics::GRPC_REQ_GAUGE.set(request_content.encoded_len() as f64);
@@ -93,6 +108,8 @@ impl SignerService {
 
             self.check_blob_status(req, storage_root).await?;
 
+            check_erasure_commitment(&self.db, req, storage_root, erasure_commitment).await?;
+
             let encoded_slices = Self::decode_encoded_slices(req)?;
 
             let res = self
@@ -126,16 +143,14 @@ impl SignerService {
                 });
             }
 
-            let hash =
-                blob_verified_hash(storage_root, req.epoch, req.quorum_id, erasure_commitment);
-            let signature = (hash * self.signer_bls_private_key).into_affine();
+            let signature = self
+                .sign_blob(req, storage_root, erasure_commitment)
+                .await?;
             let mut value = Vec::new();
             signature.serialize_uncompressed(&mut value);
             reply.signatures.push(value);
             // write slices to db
             self.db
-                .write()
-                .await
                 .put_slice(req.epoch, req.quorum_id, storage_root, encoded_slices)
                 .await
                 .map_err(|e| Status::new(Code::Internal, format!("put slice error: {:?}", e)))?;
@@ -158,72 +173,9 @@ impl SignerService {
             encoded_slice: vec![],
         };
         for req in request_content.requests.iter() {
-            let mut slices = Slices {
-                encoded_slice: vec![],
-            };
-            let storage_root: [u8; 32] = req
-                .storage_root
-                .clone()
-                .try_into()
-                .map_err(|_| Status::new(Code::InvalidArgument, "storage root"))?;
-            let maybe_assigned_slices = self
-                .db
-                .read()
-                .await
-                .get_assgined_slices(req.epoch, req.quorum_id)
-                .await
-                .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
-            match maybe_assigned_slices {
-                Some(AssignedSlices(assigned_slices)) => {
-                    let mut row_indexes = req.row_indexes.clone();
-                    row_indexes.sort_unstable();
-                    row_indexes.dedup();
-                    if row_indexes.len() > assigned_slices.len() {
-                        return Err(Status::new(Code::InvalidArgument, "invalid row indexes"));
-                    }
-                    let mut j = 0;
-                    for row_index in row_indexes.iter() {
-                        while j < assigned_slices.len() && assigned_slices[j] < *row_index as u64 {
-                            j += 1;
-                        }
-                        if j < assigned_slices.len() && assigned_slices[j] == *row_index as u64 {
-                            let maybe_slice = self
-                                .db
-                                .read()
-                                .await
-                                .get_raw_slice(
-                                    req.epoch,
-                                    req.quorum_id,
-                                    storage_root,
-                                    assigned_slices[j] as usize,
-                                )
-                                .await
-                                .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
-                            match maybe_slice {
-                                Some(slice) => {
-                                    slices.encoded_slice.push(slice);
-                                }
-                                None => {
-                                    error!("slice is missing: epoch = {:?}, quorum = {:?}, storage_root = {:?}, row_index = {:?}", req.epoch, req.quorum_id, hex::encode(storage_root), assigned_slices[j]);
-                                    return Err(Status::new(
-                                        Code::Internal,
-                                        "slice is missing".to_string(),
-                                    ));
-                                }
-                            }
-                        } else {
-                            return Err(Status::new(Code::InvalidArgument, "invalid row indexes"));
-                        }
-                    }
-                }
-                None => {
-                    return Err(Status::new(
-                        Code::Internal,
-                        format!("quorum of epoch {:?} not found", req.epoch),
-                    ));
-                }
-            }
-            reply.encoded_slice.push(slices);
+            reply
+                .encoded_slice
+                .push(retrieve_slices(&self.db, req).await?);
         }
         Ok(Response::new(reply))
     }
@@ -283,6 +235,188 @@ impl Signer for SignerService {
         timer.observe_duration();
         Ok(Response::new(status))
     }
+
+    async fn get_registration_history(
+        &self,
+        request: Request<RegistrationHistoryRequest>,
+    ) -> Result<Response<RegistrationHistoryReply>, Status> {
+        metrics::GRPC_RQE_COUNTER
+            .with_label_values(&["get_registration_history"])
+            .inc();
+        let timer = metrics::GRPC_REQ_HISTOGRAM
+            .with_label_values(&["get_registration_history"])
+            .start_timer();
+        let request_content = request.into_inner();
+        let limit = match request_content.limit {
+            0 => DEFAULT_REGISTRATION_HISTORY_LIMIT,
+            x => x as usize,
+        };
+        let records = self
+            .db
+            .get_registration_records(request_content.from_epoch, limit)
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        let reply = RegistrationHistoryReply {
+            records: records
+                .into_iter()
+                .map(registration_record_to_proto)
+                .collect(),
+        };
+        timer.observe_duration();
+        Ok(Response::new(reply))
+    }
+
+    async fn check_signature(
+        &self,
+        request: Request<CheckSignatureRequest>,
+    ) -> Result<Response<CheckSignatureReply>, Status> {
+        metrics::GRPC_RQE_COUNTER
+            .with_label_values(&["check_signature"])
+            .inc();
+        let timer = metrics::GRPC_REQ_HISTOGRAM
+            .with_label_values(&["check_signature"])
+            .start_timer();
+        let request_content = request.into_inner();
+        let req = request_content
+            .request
+            .ok_or_else(|| Status::new(Code::InvalidArgument, "sign request missing"))?;
+        let (storage_root, erasure_commitment) = Self::decode_root(&req)?;
+        let signature =
+            G1Affine::deserialize_uncompressed(&*request_content.signature).map_err(|e| {
+                Status::new(
+                    Code::InvalidArgument,
+                    format!("failed to deserialize signature: {:?}", e),
+                )
+            })?;
+        let check = self
+            .chain_state
+            .check_signature(
+                &self.bls_keys,
+                req.epoch,
+                req.quorum_id,
+                storage_root,
+                erasure_commitment,
+                signature,
+            )
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        timer.observe_duration();
+        Ok(Response::new(CheckSignatureReply {
+            signature_valid: check.signature_valid,
+            rows: check.rows,
+            hit: check.hit,
+            aggregate_matches: check.aggregate_matches,
+        }))
+    }
+}
+
+/// The assigned slices of a blob at the requested rows.
+async fn retrieve_slices(db: &Storage, req: &RetrieveRequest) -> Result<Slices, Status> {
+    let mut slices = Slices {
+        encoded_slice: vec![],
+    };
+    let storage_root: [u8; 32] = req
+        .storage_root
+        .clone()
+        .try_into()
+        .map_err(|_| Status::new(Code::InvalidArgument, "storage root"))?;
+    let maybe_assigned_slices = db
+        .get_assgined_slices(req.epoch, req.quorum_id)
+        .await
+        .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+    match maybe_assigned_slices {
+        Some(AssignedSlices(assigned_slices)) => {
+            let mut row_indexes = req.row_indexes.clone();
+            row_indexes.sort_unstable();
+            row_indexes.dedup();
+            if row_indexes.len() > assigned_slices.len() {
+                return Err(Status::new(Code::InvalidArgument, "invalid row indexes"));
+            }
+            let mut j = 0;
+            for row_index in row_indexes.iter() {
+                while j < assigned_slices.len() && assigned_slices[j] < *row_index as u64 {
+                    j += 1;
+                }
+                if j < assigned_slices.len() && assigned_slices[j] == *row_index as u64 {
+                    let maybe_slice = db
+                        .get_raw_slice(
+                            req.epoch,
+                            req.quorum_id,
+                            storage_root,
+                            assigned_slices[j] as usize,
+                        )
+                        .await
+                        .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+                    match maybe_slice {
+                        Some(slice) => {
+                            slices.encoded_slice.push(slice);
+                        }
+                        None => {
+                            error!("slice is missing: epoch = {:?}, quorum = {:?}, storage_root = {:?}, row_index = {:?}", req.epoch, req.quorum_id, hex::encode(storage_root), assigned_slices[j]);
+                            return Err(Status::new(
+                                Code::Internal,
+                                "slice is missing".to_string(),
+                            ));
+                        }
+                    }
+                } else {
+                    return Err(Status::new(Code::InvalidArgument, "invalid row indexes"));
+                }
+            }
+        }
+        None => {
+            return Err(Status::new(
+                Code::Internal,
+                format!("quorum of epoch {:?} not found", req.epoch),
+            ));
+        }
+    }
+    Ok(slices)
+}
+
+// requests are only served for uploaded blobs, whose commitments are not verified on chain yet,
+// so a commitment is only checked against the one signed before for the same blob
+//
+// `ProtectedSigner` makes the binding check and records the commitment when signing, this only
+// refuses conflicting requests before their slices are verified
+async fn check_erasure_commitment(
+    db: &Storage,
+    req: &SignRequest,
+    storage_root: [u8; 32],
+    erasure_commitment: G1Projective,
+) -> Result<(), Status> {
+    let maybe_commitment = db
+        .get_erasure_commitment(req.epoch, req.quorum_id, storage_root)
+        .await
+        .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+    match maybe_commitment {
+        Some(commitment) if commitment != serialize_commitment(erasure_commitment) => {
+            Err(Status::new(
+                Code::InvalidArgument,
+                "erasure commitment mismatches the one signed before",
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn registration_record_to_proto(record: RegistrationRecord) -> signer::RegistrationRecord {
+    signer::RegistrationRecord {
+        kind: match record.kind {
+            RegistrationKind::Signer => "signer".to_string(),
+            RegistrationKind::Epoch => "epoch".to_string(),
+        },
+        epoch: record.epoch,
+        timestamp: record.timestamp,
+        tx_hash: record.tx_hash.map_or(vec![], |x| x.to_vec()),
+        block_number: record.block_number,
+        signature: record.signature,
+        outcome: match record.outcome {
+            RegistrationOutcome::Success => "success".to_string(),
+            RegistrationOutcome::Reverted => "reverted".to_string(),
+            RegistrationOutcome::Error(e) => format!("error: {}", e),
+        },
+    }
 }
 
 pub enum VerificationError {
@@ -318,8 +452,6 @@ impl SignerService {
     ) -> Result<(), Status> {
         let maybe_blob_status = self
             .db
-            .read()
-            .await
             .get_blob_status(req.epoch, req.quorum_id, storage_root)
             .await
             .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
@@ -330,6 +462,27 @@ impl SignerService {
         }
     }
 
+    async fn sign_blob(
+        &self,
+        req: &SignRequest,
+        storage_root: [u8; 32],
+        erasure_commitment: G1Projective,
+    ) -> Result<G1Affine, Status> {
+        let request = BlobSignRequest {
+            storage_root,
+            epoch: req.epoch,
+            quorum_id: req.quorum_id,
+            erasure_commitment: serialize_commitment(erasure_commitment),
+        };
+        self.bls_signer.sign_blob(&request).await.map_err(|e| {
+            if e.downcast_ref::<SlashingProtectionError>().is_some() {
+                Status::new(Code::InvalidArgument, e.to_string())
+            } else {
+                Status::new(Code::Internal, format!("sign error: {:?}", e))
+            }
+        })
+    }
+
     fn decode_root(req: &SignRequest) -> Result<([u8; 32], G1Projective), Status> {
         let storage_root: [u8; 32] = req
             .storage_root
@@ -395,12 +548,7 @@ impl SignerService {
             return Err("quorum_id out of bound".into());
         }
         // check assigned slices
-        let maybe_assigned_slices = self
-            .db
-            .read()
-            .await
-            .get_assgined_slices(epoch, quorum_id)
-            .await?;
+        let maybe_assigned_slices = self.db.get_assgined_slices(epoch, quorum_id).await?;
         match maybe_assigned_slices {
             Some(AssignedSlices(assigned_slices)) => {
                 self.verify_assigned_slices(
@@ -465,30 +613,13 @@ impl SignerService {
     }
 }
 
-fn u256_to_u8_array(x: U256) -> Vec<u8> {
-    let mut bytes = [0; 32];
-    x.to_big_endian(&mut bytes);
-    bytes.to_vec()
-}
-
-pub fn blob_verified_hash(
-    data_root: [u8; 32],
-    epoch: u64,
-    quorum_id: u64,
-    erasure_commitment: G1Projective,
-) -> G1Affine {
-    let g1_point = serialize_g1_point(erasure_commitment.into_affine());
-    let hash = keccak256(
-        abi::encode_packed(&[
-            Token::FixedBytes(data_root.to_vec()),
-            Token::FixedBytes(u256_to_u8_array(U256::from(epoch))),
-            Token::FixedBytes(u256_to_u8_array(U256::from(quorum_id))),
-            Token::FixedBytes(u256_to_u8_array(g1_point.x)),
-            Token::FixedBytes(u256_to_u8_array(g1_point.y)),
-        ])
-        .unwrap(),
-    );
-    map_to_g1(hash.to_vec())
+fn serialize_commitment(erasure_commitment: G1Projective) -> Vec<u8> {
+    let mut value = Vec::new();
+    erasure_commitment
+        .into_affine()
+        .serialize_uncompressed(&mut value)
+        .unwrap();
+    value
 }
 
 #[cfg(test)]
@@ -498,11 +629,90 @@ mod tests {
     use ark_bn254::g1;
     use ark_ec::AffineRepr;
     use ark_ff::Fp;
-    use utils::hex_to_bytes;
+    use utils::{blob_verified_hash, hash_to_curve::G1HashScheme, hex_to_bytes};
     use zg_encoder::constants::G1A;
 
     use super::*;
 
+    #[tokio::test]
+    async fn retrieve_slices_test() {
+        let db = Storage::in_memory();
+        let request = |row_indexes: Vec<u32>| RetrieveRequest {
+            epoch: 1,
+            quorum_id: 0,
+            storage_root: vec![7; 32],
+            row_indexes,
+        };
+        let code = |result: Result<Slices, Status>| result.unwrap_err().code();
+        assert_eq!(
+            code(retrieve_slices(&db, &request(vec![])).await),
+            Code::Internal
+        );
+
+        db.put_quorums(1, vec![AssignedSlices(vec![2, 5])])
+            .await
+            .unwrap();
+        assert!(retrieve_slices(&db, &request(vec![]))
+            .await
+            .unwrap()
+            .encoded_slice
+            .is_empty());
+        // rows not assigned to the quorum
+        assert_eq!(
+            code(retrieve_slices(&db, &request(vec![3])).await),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(retrieve_slices(&db, &request(vec![2, 5, 6])).await),
+            Code::InvalidArgument
+        );
+        // assigned rows whose slices are not stored
+        let status = retrieve_slices(&db, &request(vec![5, 5]))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            (status.code(), status.message()),
+            (Code::Internal, "slice is missing")
+        );
+        let mut invalid_root = request(vec![2]);
+        invalid_root.storage_root.pop();
+        assert_eq!(
+            code(retrieve_slices(&db, &invalid_root).await),
+            Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn check_erasure_commitment_test() {
+        let db = Storage::in_memory();
+        let req = SignRequest {
+            epoch: 1,
+            quorum_id: 0,
+            ..Default::default()
+        };
+        let commitment = G1Projective::from(G1Affine::generator());
+        let other = commitment + commitment;
+        // nothing signed for the blob yet
+        assert!(check_erasure_commitment(&db, &req, [1; 32], commitment)
+            .await
+            .is_ok());
+
+        db.put_erasure_commitment(1, 0, [1; 32], serialize_commitment(commitment))
+            .await
+            .unwrap();
+        assert!(check_erasure_commitment(&db, &req, [1; 32], commitment)
+            .await
+            .is_ok());
+        let status = check_erasure_commitment(&db, &req, [1; 32], other)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        // commitments of other blobs are unrelated
+        assert!(check_erasure_commitment(&db, &req, [2; 32], other)
+            .await
+            .is_ok());
+    }
+
     #[test]
     fn blob_verified_hash_test() {
         let a = g1::G1Affine::generator() * Fr::from(1);
@@ -514,6 +724,7 @@ mod tests {
             1,
             2,
             G1Projective::new(Fp::from(1), Fp::from(2), Fp::from(1)),
+            G1HashScheme::TryAndIncrement,
         );
         assert_eq!(
             hash,
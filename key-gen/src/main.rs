@@ -1,8 +1,316 @@
+use std::{error::Error, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use ark_bn254::{Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
 use ark_std::{rand::thread_rng, UniformRand};
-use std::error::Error;
+use chain_state::signers_handler::{
+    epoch_registration_hash, register_next_epoch_calldata, register_signer_calldata,
+    serialize_g1_point, serialize_g2_point, signer_registration_hash, update_socket_calldata,
+};
+use chain_utils::{DA_REGISTRY_ADDRESS, DA_SIGNER_ADDRESS};
+use clap::{arg, command, ArgMatches, Command};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Bytes, H160, H256},
+};
+use serde_json::{json, Value};
+use utils::{
+    hash_to_curve::G1HashScheme,
+    keystore::{decrypt_bls_key, encrypt_bls_key, encrypt_eth_key, read_keystore_password},
+};
+
+fn cli_app<'a>() -> Command<'a> {
+    let keystore_args = [
+        arg!(--"keystore-dir" <DIR> "Writes the key to an encrypted keystore in this directory")
+            .required(false),
+        arg!(--name <NAME> "File name of the keystore").required(false),
+        arg!(--"password-file" <FILE> "Reads the keystore password from this file instead of ZG_DA_KEYSTORE_PASSWORD")
+            .required(false),
+    ];
+    let bls_key_args = [
+        arg!(--key <KEY> "BLS private key")
+            .required(false)
+            .required_unless_present("keystore"),
+        arg!(--keystore <FILE> "Reads the BLS private key from this keystore").required(false),
+        arg!(--"password-file" <FILE> "Reads the keystore password from this file instead of ZG_DA_KEYSTORE_PASSWORD")
+            .required(false),
+    ];
+    let hash_scheme_arg = arg!(--"hash-scheme" <SCHEME> "How messages are hashed to G1: try_and_increment (default) or rfc9380")
+        .required(false);
+    command!()
+        .about("Prints a random BLS private key if no subcommand is given")
+        .subcommand(
+            Command::new("bls")
+                .about("Generates a BLS key and its public keys")
+                .args(&keystore_args),
+        )
+        .subcommand(
+            Command::new("pubkey")
+                .about("Derives the G1 and G2 public keys of a BLS key")
+                .args(&bls_key_args),
+        )
+        .subcommand(
+            Command::new("sign-registration")
+                .about("Signs the signer registration message")
+                .args(&bls_key_args)
+                .arg(arg!(--signer <ADDRESS> "Signer eth address"))
+                .arg(arg!(--"chain-id" <ID> "Chain id"))
+                .arg(hash_scheme_arg.clone()),
+        )
+        .subcommand(
+            Command::new("register-signer-tx")
+                .about("Prints the unsigned registerSigner transaction")
+                .args(&bls_key_args)
+                .arg(arg!(--signer <ADDRESS> "Signer eth address"))
+                .arg(arg!(--"chain-id" <ID> "Chain id"))
+                .arg(hash_scheme_arg.clone())
+                .arg(arg!(--socket <SOCKET> "Public grpc socket of the signer")),
+        )
+        .subcommand(
+            Command::new("register-epoch-tx")
+                .about("Prints the unsigned registerNextEpoch transaction")
+                .args(&bls_key_args)
+                .arg(arg!(--signer <ADDRESS> "Signer eth address"))
+                .arg(arg!(--"chain-id" <ID> "Chain id"))
+                .arg(hash_scheme_arg.clone())
+                .arg(arg!(--epoch <EPOCH> "Epoch to register for")),
+        )
+        .subcommand(
+            Command::new("update-socket-tx")
+                .about("Prints the unsigned updateSocket transaction")
+                .arg(arg!(--socket <SOCKET> "Public grpc socket of the signer")),
+        )
+        .subcommand(
+            Command::new("eth")
+                .about("Generates an eth key")
+                .args(&keystore_args),
+        )
+}
+
+fn parse_bls_key(matches: &ArgMatches) -> Result<Fr> {
+    match matches.value_of("keystore") {
+        Some(path) => decrypt_bls_key(path, &keystore_password(matches)?),
+        None => Fr::from_str(matches.value_of("key").unwrap())
+            .map_err(|_| anyhow!("Cannot parse bls private key")),
+    }
+}
+
+fn parse_signer(matches: &ArgMatches) -> Result<(H160, u64, G1HashScheme)> {
+    Ok((
+        H160::from_str(matches.value_of("signer").unwrap())?,
+        u64::from_str(matches.value_of("chain-id").unwrap())?,
+        match matches.value_of("hash-scheme") {
+            Some(x) => x.parse()?,
+            None => G1HashScheme::default(),
+        },
+    ))
+}
+
+fn unsigned_tx(to: &str, data: Bytes) -> Value {
+    json!({ "to": to, "data": data.to_string() })
+}
+
+fn bls_public_keys(key: Fr) -> Value {
+    let pk_g1 = serialize_g1_point((G1Affine::generator() * key).into_affine());
+    let pk_g2 = serialize_g2_point((G2Affine::generator() * key).into_affine());
+    json!({
+        "pk_g1": {"x": pk_g1.x.to_string(), "y": pk_g1.y.to_string()},
+        "pk_g2": {
+            "x": [pk_g2.x[0].to_string(), pk_g2.x[1].to_string()],
+            "y": [pk_g2.y[0].to_string(), pk_g2.y[1].to_string()],
+        },
+    })
+}
+
+fn keystore_password(matches: &ArgMatches) -> Result<String> {
+    read_keystore_password(matches.value_of("password-file"))
+}
+
+fn generate_bls(matches: &ArgMatches) -> Result<Value> {
+    let key = Fr::rand(&mut thread_rng());
+    let mut output = bls_public_keys(key);
+    match matches.value_of("keystore-dir") {
+        Some(dir) => {
+            let name = matches.value_of("name").unwrap_or("bls_key.json");
+            encrypt_bls_key(dir, name, key, &keystore_password(matches)?)?;
+            output["keystore"] = json!(format!("{}/{}", dir, name));
+        }
+        None => output["private_key"] = json!(key.to_string()),
+    }
+    Ok(output)
+}
+
+fn sign_registration(matches: &ArgMatches) -> Result<Value> {
+    let key = parse_bls_key(matches)?;
+    let (signer, chain_id, hash_scheme) = parse_signer(matches)?;
+    let signature = serialize_g1_point(
+        (signer_registration_hash(signer, chain_id, hash_scheme) * key).into_affine(),
+    );
+    Ok(json!({
+        "signature": {"x": signature.x.to_string(), "y": signature.y.to_string()},
+    }))
+}
+
+fn register_signer_tx(matches: &ArgMatches) -> Result<Value> {
+    let key = parse_bls_key(matches)?;
+    let (signer, chain_id, hash_scheme) = parse_signer(matches)?;
+    let signature = (signer_registration_hash(signer, chain_id, hash_scheme) * key).into_affine();
+    let data = register_signer_calldata(
+        signer,
+        matches.value_of("socket").unwrap().to_string(),
+        (G1Affine::generator() * key).into_affine(),
+        (G2Affine::generator() * key).into_affine(),
+        signature,
+    );
+    Ok(unsigned_tx(DA_REGISTRY_ADDRESS, data))
+}
+
+fn register_epoch_tx(matches: &ArgMatches) -> Result<Value> {
+    let key = parse_bls_key(matches)?;
+    let (signer, chain_id, hash_scheme) = parse_signer(matches)?;
+    let epoch = u64::from_str(matches.value_of("epoch").unwrap())?;
+    let signature =
+        (epoch_registration_hash(signer, epoch, chain_id, hash_scheme) * key).into_affine();
+    Ok(unsigned_tx(
+        DA_REGISTRY_ADDRESS,
+        register_next_epoch_calldata(signature),
+    ))
+}
+
+fn update_socket_tx(matches: &ArgMatches) -> Result<Value> {
+    let socket = matches.value_of("socket").unwrap().to_string();
+    Ok(unsigned_tx(
+        DA_SIGNER_ADDRESS,
+        update_socket_calldata(socket),
+    ))
+}
+
+fn generate_eth(matches: &ArgMatches) -> Result<Value> {
+    let wallet = LocalWallet::new(&mut thread_rng());
+    let key = H256::from_slice(&wallet.signer().to_bytes());
+    let mut output = json!({ "address": format!("{:?}", wallet.address()) });
+    match matches.value_of("keystore-dir") {
+        Some(dir) => {
+            let name = matches.value_of("name").unwrap_or("eth_key.json");
+            encrypt_eth_key(dir, name, key, &keystore_password(matches)?)?;
+            output["keystore"] = json!(format!("{}/{}", dir, name));
+        }
+        None => output["private_key"] = json!(format!("{:?}", key)),
+    }
+    Ok(output)
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut rng = thread_rng();
-    println!("{}", ark_bn254::Fr::rand(&mut rng));
+    let matches = cli_app().get_matches();
+    let output = match matches.subcommand() {
+        Some(("bls", m)) => generate_bls(m)?,
+        Some(("pubkey", m)) => bls_public_keys(parse_bls_key(m)?),
+        Some(("sign-registration", m)) => sign_registration(m)?,
+        Some(("register-signer-tx", m)) => register_signer_tx(m)?,
+        Some(("register-epoch-tx", m)) => register_epoch_tx(m)?,
+        Some(("update-socket-tx", m)) => update_socket_tx(m)?,
+        Some(("eth", m)) => generate_eth(m)?,
+        _ => {
+            println!("{}", Fr::rand(&mut thread_rng()));
+            return Ok(());
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&output)?);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::{Fq, Fq2};
+    use chain_state::signature_check::verify_bls_signature;
+    use std::{env, fs, process};
+
+    const SIGNER: &str = "0x0000000000000000000000000000000000000001";
+
+    fn run(args: &[&str]) -> Value {
+        let matches = cli_app().get_matches_from([&["key-gen"], args].concat());
+        match matches.subcommand() {
+            Some(("bls", m)) => generate_bls(m),
+            Some(("pubkey", m)) => parse_bls_key(m).map(bls_public_keys),
+            Some(("sign-registration", m)) => sign_registration(m),
+            _ => unreachable!(),
+        }
+        .unwrap()
+    }
+
+    fn fq(value: &Value) -> Fq {
+        Fq::from_str(value.as_str().unwrap()).unwrap()
+    }
+
+    fn g1(value: &Value) -> G1Affine {
+        G1Affine::new(fq(&value["x"]), fq(&value["y"]))
+    }
+
+    fn g2(value: &Value) -> G2Affine {
+        G2Affine::new(
+            Fq2::new(fq(&value["x"][0]), fq(&value["x"][1])),
+            Fq2::new(fq(&value["y"][0]), fq(&value["y"][1])),
+        )
+    }
+
+    #[test]
+    fn sign_registration_test() {
+        let public_keys = run(&["pubkey", "--key", "12345"]);
+        assert_eq!(
+            g1(&public_keys["pk_g1"]),
+            (G1Affine::generator() * Fr::from(12345u64)).into_affine()
+        );
+        let pk_g2 = g2(&public_keys["pk_g2"]);
+        for scheme in ["try_and_increment", "rfc9380"] {
+            let output = run(&[
+                "sign-registration",
+                "--key",
+                "12345",
+                "--signer",
+                SIGNER,
+                "--chain-id",
+                "16600",
+                "--hash-scheme",
+                scheme,
+            ]);
+            let hash = signer_registration_hash(
+                H160::from_str(SIGNER).unwrap(),
+                16600,
+                scheme.parse().unwrap(),
+            );
+            // the signature verifies against the printed public key
+            assert!(verify_bls_signature(hash, g1(&output["signature"]), pk_g2));
+        }
+    }
+
+    #[test]
+    fn bls_keystore_test() {
+        let dir = env::temp_dir().join(format!("zg-da-node-key-gen-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let password_file = dir.join("password");
+        fs::write(&password_file, "password\n").unwrap();
+        let dir_arg = dir.to_str().unwrap();
+        let password_arg = password_file.to_str().unwrap();
+
+        let generated = run(&[
+            "bls",
+            "--keystore-dir",
+            dir_arg,
+            "--password-file",
+            password_arg,
+        ]);
+        assert!(generated.get("private_key").is_none());
+        let public_keys = run(&[
+            "pubkey",
+            "--keystore",
+            generated["keystore"].as_str().unwrap(),
+            "--password-file",
+            password_arg,
+        ]);
+        assert_eq!(public_keys["pk_g1"], generated["pk_g1"]);
+        assert_eq!(public_keys["pk_g2"], generated["pk_g2"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
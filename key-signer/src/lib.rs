@@ -0,0 +1,54 @@
+#[macro_use]
+extern crate tracing;
+
+mod local;
+mod protection;
+mod remote;
+mod rotation;
+pub mod stand_in;
+
+use anyhow::Result;
+use ark_bn254::{G1Affine, G2Affine};
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2718::TypedTransaction, Signature, H160};
+use serde::{Deserialize, Serialize};
+
+pub use local::LocalBlsSigner;
+pub use protection::{
+    MemorySigningHistory, ProtectedSigner, SigningHistory, SlashingProtectionError,
+    StorageSigningHistory,
+};
+pub use remote::RemoteSigner;
+pub use rotation::BlsKeyRing;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlobSignRequest {
+    pub storage_root: [u8; 32],
+    pub epoch: u64,
+    pub quorum_id: u64,
+    // uncompressed serialization of the erasure commitment
+    pub erasure_commitment: Vec<u8>,
+}
+
+/// Holder of the signer BLS key.
+#[async_trait]
+pub trait BlsSigner: Send + Sync {
+    fn public_key_g1(&self) -> G1Affine;
+
+    fn public_key_g2(&self) -> G2Affine;
+
+    /// Signs a registration message already hashed to G1.
+    async fn sign_registration(&self, hash: G1Affine) -> Result<G1Affine>;
+
+    /// Signs the verified hash of a blob.
+    async fn sign_blob(&self, request: &BlobSignRequest) -> Result<G1Affine>;
+}
+
+/// Holder of the signer eth account key.
+#[async_trait]
+pub trait EthSigner: Send + Sync {
+    fn address(&self) -> H160;
+
+    /// Signs the transaction, whose chain id must be set.
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature>;
+}
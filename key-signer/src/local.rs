@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use ark_bn254::{Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_serialize::CanonicalDeserialize;
+use async_trait::async_trait;
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip2718::TypedTransaction, Signature, H160},
+};
+use utils::{blob_verified_hash, hash_to_curve::G1HashScheme};
+
+use crate::{BlobSignRequest, BlsSigner, EthSigner};
+
+pub struct LocalBlsSigner {
+    private_key: Fr,
+    public_key_g1: G1Affine,
+    public_key_g2: G2Affine,
+    hash_scheme: G1HashScheme,
+}
+
+impl LocalBlsSigner {
+    pub fn new(private_key: Fr) -> Self {
+        Self {
+            private_key,
+            public_key_g1: (G1Affine::generator() * private_key).into_affine(),
+            public_key_g2: (G2Affine::generator() * private_key).into_affine(),
+            hash_scheme: G1HashScheme::default(),
+        }
+    }
+
+    /// Hashes blobs to G1 with `hash_scheme` instead of the default scheme.
+    pub fn with_hash_scheme(mut self, hash_scheme: G1HashScheme) -> Self {
+        self.hash_scheme = hash_scheme;
+        self
+    }
+}
+
+#[async_trait]
+impl BlsSigner for LocalBlsSigner {
+    fn public_key_g1(&self) -> G1Affine {
+        self.public_key_g1
+    }
+
+    fn public_key_g2(&self) -> G2Affine {
+        self.public_key_g2
+    }
+
+    async fn sign_registration(&self, hash: G1Affine) -> Result<G1Affine> {
+        Ok((hash * self.private_key).into_affine())
+    }
+
+    async fn sign_blob(&self, request: &BlobSignRequest) -> Result<G1Affine> {
+        let erasure_commitment = G1Affine::deserialize_uncompressed(&*request.erasure_commitment)
+            .map_err(|e| anyhow!("invalid erasure commitment: {:?}", e))?;
+        let hash = blob_verified_hash(
+            request.storage_root,
+            request.epoch,
+            request.quorum_id,
+            G1Projective::from(erasure_commitment),
+            self.hash_scheme,
+        );
+        Ok((hash * self.private_key).into_affine())
+    }
+}
+
+#[async_trait]
+impl EthSigner for LocalWallet {
+    fn address(&self) -> H160 {
+        Signer::address(self)
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature> {
+        Ok(Signer::sign_transaction(self, tx).await?)
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use anyhow::{bail, Result};
+use ark_bn254::{G1Affine, G2Affine};
+use async_trait::async_trait;
+use storage::{blob_status_db::BlobStatusDB, Storage};
+use tokio::sync::Mutex;
+
+use crate::{BlobSignRequest, BlsSigner};
+
+/// Returned when asked to sign a commitment different from the one signed before for a root.
+#[derive(Debug)]
+pub struct SlashingProtectionError(pub BlobSignRequest);
+
+impl fmt::Display for SlashingProtectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "erasure commitment of root {} in epoch {} quorum {} mismatches the one signed before",
+            hex::encode(self.0.storage_root),
+            self.0.epoch,
+            self.0.quorum_id
+        )
+    }
+}
+
+impl std::error::Error for SlashingProtectionError {}
+
+/// Remembers the erasure commitment signed for each root.
+#[async_trait]
+pub trait SigningHistory: Send + Sync {
+    /// Records the commitment of the request, failing with `SlashingProtectionError` if a
+    /// different one was recorded for the same root.
+    async fn check_and_record(&self, request: &BlobSignRequest) -> Result<()>;
+}
+
+// epoch, quorum id and storage root
+type BlobKey = (u64, u64, [u8; 32]);
+
+#[derive(Default)]
+pub struct MemorySigningHistory {
+    commitments: Mutex<HashMap<BlobKey, Vec<u8>>>,
+}
+
+#[async_trait]
+impl SigningHistory for MemorySigningHistory {
+    async fn check_and_record(&self, request: &BlobSignRequest) -> Result<()> {
+        let mut commitments = self.commitments.lock().await;
+        let key = (request.epoch, request.quorum_id, request.storage_root);
+        match commitments.get(&key) {
+            Some(commitment) if *commitment != request.erasure_commitment => {
+                bail!(SlashingProtectionError(request.clone()))
+            }
+            Some(_) => Ok(()),
+            None => {
+                commitments.insert(key, request.erasure_commitment.clone());
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct StorageSigningHistory {
+    db: Arc<Storage>,
+    // serializes check and record, so that concurrent requests cannot record different commitments
+    lock: Mutex<()>,
+}
+
+impl StorageSigningHistory {
+    pub fn new(db: Arc<Storage>) -> Self {
+        Self {
+            db,
+            lock: Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl SigningHistory for StorageSigningHistory {
+    async fn check_and_record(&self, request: &BlobSignRequest) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        match self
+            .db
+            .get_erasure_commitment(request.epoch, request.quorum_id, request.storage_root)
+            .await?
+        {
+            Some(commitment) if commitment != request.erasure_commitment => {
+                bail!(SlashingProtectionError(request.clone()))
+            }
+            Some(_) => Ok(()),
+            None => {
+                self.db
+                    .put_erasure_commitment(
+                        request.epoch,
+                        request.quorum_id,
+                        request.storage_root,
+                        request.erasure_commitment.clone(),
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+/// Checks every blob against the signing history before passing it to the inner signer.
+pub struct ProtectedSigner {
+    inner: Arc<dyn BlsSigner>,
+    history: Arc<dyn SigningHistory>,
+}
+
+impl ProtectedSigner {
+    pub fn new(inner: Arc<dyn BlsSigner>, history: Arc<dyn SigningHistory>) -> Self {
+        Self { inner, history }
+    }
+}
+
+#[async_trait]
+impl BlsSigner for ProtectedSigner {
+    fn public_key_g1(&self) -> G1Affine {
+        self.inner.public_key_g1()
+    }
+
+    fn public_key_g2(&self) -> G2Affine {
+        self.inner.public_key_g2()
+    }
+
+    async fn sign_registration(&self, hash: G1Affine) -> Result<G1Affine> {
+        self.inner.sign_registration(hash).await
+    }
+
+    async fn sign_blob(&self, request: &BlobSignRequest) -> Result<G1Affine> {
+        if let Err(e) = self.history.check_and_record(request).await {
+            warn!("refuse to sign blob: {:?}", e);
+            return Err(e);
+        }
+        self.inner.sign_blob(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn storage_signing_history_test() {
+        let history = StorageSigningHistory::new(Arc::new(Storage::in_memory()));
+        let request = |erasure_commitment: Vec<u8>| BlobSignRequest {
+            storage_root: [1; 32],
+            epoch: 2,
+            quorum_id: 3,
+            erasure_commitment,
+        };
+        history.check_and_record(&request(vec![4])).await.unwrap();
+        history.check_and_record(&request(vec![4])).await.unwrap();
+        let err = history
+            .check_and_record(&request(vec![5]))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<SlashingProtectionError>().is_some());
+    }
+}
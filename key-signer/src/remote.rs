@@ -0,0 +1,244 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::{G1Affine, G2Affine};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use async_trait::async_trait;
+use ethers::types::{transaction::eip2718::TypedTransaction, Signature, H160};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{BlobSignRequest, BlsSigner, EthSigner, SlashingProtectionError};
+
+const REMOTE_SIGNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct PublicKeyReply {
+    pub address: H160,
+    pub pk_g1: String,
+    pub pk_g2: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SignRegistrationRequest {
+    pub hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SignTransactionRequest {
+    pub tx: TypedTransaction,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SignatureReply {
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TransactionSignatureReply {
+    pub signature: Signature,
+}
+
+pub(crate) fn encode_point<T: CanonicalSerialize>(point: &T) -> String {
+    let mut value = Vec::new();
+    point.serialize_uncompressed(&mut value).unwrap();
+    hex::encode(value)
+}
+
+pub(crate) fn decode_point<T: CanonicalDeserialize>(value: &str) -> Result<T> {
+    T::deserialize_uncompressed(&*hex::decode(value)?)
+        .map_err(|e| anyhow!("invalid curve point: {:?}", e))
+}
+
+/// Signer holding the keys in another process, reached over HTTP.
+///
+/// Endpoints take and return JSON: `GET /public_key`, `POST /sign_registration`,
+/// `POST /sign_blob` (409 when refused by slashing protection) and `POST /sign_transaction`.
+pub struct RemoteSigner {
+    url: String,
+    client: reqwest::Client,
+    address: H160,
+    public_key_g1: G1Affine,
+    public_key_g2: G2Affine,
+}
+
+impl RemoteSigner {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let url = url.trim_end_matches('/').to_string();
+        let client = reqwest::Client::builder()
+            .timeout(REMOTE_SIGNER_TIMEOUT)
+            .build()?;
+        let resp = client.get(format!("{}/public_key", url)).send().await?;
+        if !resp.status().is_success() {
+            bail!(anyhow!("remote signer returns {:?}", resp.status()));
+        }
+        let reply: PublicKeyReply = serde_json::from_str(&resp.text().await?)?;
+        info!("connected to remote signer of account {:?}", reply.address);
+        Ok(Self {
+            url,
+            client,
+            address: reply.address,
+            public_key_g1: decode_point(&reply.pk_g1)?,
+            public_key_g2: decode_point(&reply.pk_g2)?,
+        })
+    }
+
+    async fn post<T: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<(StatusCode, Option<R>)> {
+        let resp = self
+            .client
+            .post(format!("{}{}", self.url, path))
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(body)?)
+            .send()
+            .await?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Ok((status, None));
+        }
+        Ok((status, Some(serde_json::from_str(&resp.text().await?)?)))
+    }
+
+    async fn post_expect<T: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<R> {
+        match self.post(path, body).await? {
+            (_, Some(reply)) => Ok(reply),
+            (status, None) => bail!(anyhow!("remote signer returns {:?} on {}", status, path)),
+        }
+    }
+}
+
+#[async_trait]
+impl BlsSigner for RemoteSigner {
+    fn public_key_g1(&self) -> G1Affine {
+        self.public_key_g1
+    }
+
+    fn public_key_g2(&self) -> G2Affine {
+        self.public_key_g2
+    }
+
+    async fn sign_registration(&self, hash: G1Affine) -> Result<G1Affine> {
+        let reply: SignatureReply = self
+            .post_expect(
+                "/sign_registration",
+                &SignRegistrationRequest {
+                    hash: encode_point(&hash),
+                },
+            )
+            .await?;
+        decode_point(&reply.signature)
+    }
+
+    async fn sign_blob(&self, request: &BlobSignRequest) -> Result<G1Affine> {
+        match self
+            .post::<_, SignatureReply>("/sign_blob", request)
+            .await?
+        {
+            (_, Some(reply)) => decode_point(&reply.signature),
+            (StatusCode::CONFLICT, None) => bail!(SlashingProtectionError(request.clone())),
+            (status, None) => bail!(anyhow!("remote signer returns {:?} on /sign_blob", status)),
+        }
+    }
+}
+
+#[async_trait]
+impl EthSigner for RemoteSigner {
+    fn address(&self) -> H160 {
+        self.address
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature> {
+        let reply: TransactionSignatureReply = self
+            .post_expect(
+                "/sign_transaction",
+                &SignTransactionRequest { tx: tx.clone() },
+            )
+            .await?;
+        Ok(reply.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use ark_ec::{AffineRepr, CurveGroup};
+    use ethers::{
+        signers::{LocalWallet, Signer},
+        types::TransactionRequest,
+    };
+
+    use super::*;
+    use crate::{stand_in, LocalBlsSigner};
+
+    #[tokio::test]
+    async fn remote_signer_test() {
+        let key = Fr::from(7u64);
+        let wallet = LocalWallet::from_bytes(&[1u8; 32])
+            .unwrap()
+            .with_chain_id(1u64);
+        let addr = stand_in::serve("127.0.0.1:0".parse().unwrap(), key, wallet.clone())
+            .await
+            .unwrap();
+        let remote = RemoteSigner::connect(&format!("http://{}", addr))
+            .await
+            .unwrap();
+        let local = LocalBlsSigner::new(key);
+        assert_eq!(remote.public_key_g1(), local.public_key_g1());
+        assert_eq!(remote.public_key_g2(), local.public_key_g2());
+        assert_eq!(EthSigner::address(&remote), Signer::address(&wallet));
+
+        let hash = (G1Affine::generator() * Fr::from(3u64)).into_affine();
+        assert_eq!(
+            remote.sign_registration(hash).await.unwrap(),
+            local.sign_registration(hash).await.unwrap()
+        );
+
+        let mut commitment = Vec::new();
+        G1Affine::generator()
+            .serialize_uncompressed(&mut commitment)
+            .unwrap();
+        let request = BlobSignRequest {
+            storage_root: [1; 32],
+            epoch: 1,
+            quorum_id: 0,
+            erasure_commitment: commitment,
+        };
+        assert_eq!(
+            remote.sign_blob(&request).await.unwrap(),
+            local.sign_blob(&request).await.unwrap()
+        );
+        assert!(remote.sign_blob(&request).await.is_ok());
+
+        let mut other_commitment = Vec::new();
+        (G1Affine::generator() * Fr::from(2u64))
+            .into_affine()
+            .serialize_uncompressed(&mut other_commitment)
+            .unwrap();
+        let conflicting = BlobSignRequest {
+            erasure_commitment: other_commitment,
+            ..request
+        };
+        let err = remote.sign_blob(&conflicting).await.unwrap_err();
+        assert!(err.downcast_ref::<SlashingProtectionError>().is_some());
+
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(H160::zero())
+            .nonce(0)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1u64)
+            .into();
+        let signature = remote.sign_transaction(&tx).await.unwrap();
+        assert_eq!(
+            signature.recover(tx.sighash()).unwrap(),
+            Signer::address(&wallet)
+        );
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::Result;
+use ark_bn254::{G1Affine, G2Affine};
+use async_trait::async_trait;
+
+use crate::{BlobSignRequest, BlsSigner};
+
+/// The current BLS key, and optionally the next key taking over from a given epoch.
+///
+/// Blobs are signed with the key of the epoch in the request, so signatures stay valid for
+/// epochs on both sides of the rotation. Registration messages are signed with the current key.
+///
+/// The contracts cannot replace a registered key, so the next key is registered out of band and
+/// only signs once the chain reports it, see `set_next_registered`.
+pub struct BlsKeyRing {
+    current: Arc<dyn BlsSigner>,
+    next: Option<(u64, Arc<dyn BlsSigner>)>,
+    next_registered: AtomicBool,
+}
+
+impl BlsKeyRing {
+    pub fn new(current: Arc<dyn BlsSigner>) -> Self {
+        Self {
+            current,
+            next: None,
+            next_registered: AtomicBool::new(false),
+        }
+    }
+
+    /// Switches to `next` from `rotation_epoch` on, once it is registered.
+    pub fn with_next(mut self, next: Arc<dyn BlsSigner>, rotation_epoch: u64) -> Self {
+        self.next = Some((rotation_epoch, next));
+        self
+    }
+
+    /// Records whether the chain reports the next key as the signer's key.
+    pub fn set_next_registered(&self, registered: bool) {
+        self.next_registered.store(registered, Ordering::Release);
+    }
+
+    /// The epoch the next key takes over from and the next key, if a rotation is scheduled.
+    pub fn rotation(&self) -> Option<(u64, &Arc<dyn BlsSigner>)> {
+        self.next.as_ref().map(|(epoch, signer)| (*epoch, signer))
+    }
+
+    /// The key used to sign for `epoch`.
+    pub fn at(&self, epoch: u64) -> &Arc<dyn BlsSigner> {
+        match &self.next {
+            Some((rotation_epoch, next))
+                if epoch >= *rotation_epoch && self.next_registered.load(Ordering::Acquire) =>
+            {
+                next
+            }
+            _ => &self.current,
+        }
+    }
+}
+
+#[async_trait]
+impl BlsSigner for BlsKeyRing {
+    fn public_key_g1(&self) -> G1Affine {
+        self.current.public_key_g1()
+    }
+
+    fn public_key_g2(&self) -> G2Affine {
+        self.current.public_key_g2()
+    }
+
+    async fn sign_registration(&self, hash: G1Affine) -> Result<G1Affine> {
+        self.current.sign_registration(hash).await
+    }
+
+    async fn sign_blob(&self, request: &BlobSignRequest) -> Result<G1Affine> {
+        self.at(request.epoch).sign_blob(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+    use ark_ec::AffineRepr;
+    use ark_serialize::CanonicalSerialize;
+
+    use super::*;
+    use crate::LocalBlsSigner;
+
+    #[tokio::test]
+    async fn sign_by_request_epoch_test() {
+        let current = Arc::new(LocalBlsSigner::new(Fr::from(3u64)));
+        let next = Arc::new(LocalBlsSigner::new(Fr::from(5u64)));
+        let ring = BlsKeyRing::new(current.clone()).with_next(next.clone(), 10);
+
+        let mut commitment = Vec::new();
+        G1Affine::generator()
+            .serialize_uncompressed(&mut commitment)
+            .unwrap();
+        let request = |epoch| BlobSignRequest {
+            storage_root: [1; 32],
+            epoch,
+            quorum_id: 0,
+            erasure_commitment: commitment.clone(),
+        };
+        assert_eq!(
+            ring.sign_blob(&request(9)).await.unwrap(),
+            current.sign_blob(&request(9)).await.unwrap()
+        );
+        // the current key signs on until the next one is registered
+        assert_eq!(
+            ring.sign_blob(&request(10)).await.unwrap(),
+            current.sign_blob(&request(10)).await.unwrap()
+        );
+        ring.set_next_registered(true);
+        assert_eq!(
+            ring.sign_blob(&request(9)).await.unwrap(),
+            current.sign_blob(&request(9)).await.unwrap()
+        );
+        assert_eq!(
+            ring.sign_blob(&request(10)).await.unwrap(),
+            next.sign_blob(&request(10)).await.unwrap()
+        );
+        assert_eq!(ring.public_key_g1(), current.public_key_g1());
+    }
+}
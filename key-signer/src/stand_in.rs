@@ -0,0 +1,122 @@
+//! A minimal remote signer serving local keys, for testing `RemoteSigner` deployments.
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use ark_bn254::Fr;
+use ethers::signers::{LocalWallet, Signer};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    remote::{
+        decode_point, encode_point, PublicKeyReply, SignRegistrationRequest,
+        SignTransactionRequest, SignatureReply, TransactionSignatureReply,
+    },
+    BlobSignRequest, BlsSigner, EthSigner, LocalBlsSigner, MemorySigningHistory, ProtectedSigner,
+    SlashingProtectionError,
+};
+
+struct StandIn {
+    bls_signer: ProtectedSigner,
+    wallet: LocalWallet,
+}
+
+/// Serves the keys on `addr` until the runtime stops, returning the bound address.
+pub async fn serve(addr: SocketAddr, bls_key: Fr, wallet: LocalWallet) -> Result<SocketAddr> {
+    let stand_in = Arc::new(StandIn {
+        bls_signer: ProtectedSigner::new(
+            Arc::new(LocalBlsSigner::new(bls_key)),
+            Arc::new(MemorySigningHistory::default()),
+        ),
+        wallet,
+    });
+    let make_svc = make_service_fn(move |_| {
+        let stand_in = stand_in.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let stand_in = stand_in.clone();
+                async move { Ok::<_, Infallible>(stand_in.handle(req).await) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)?.serve(make_svc);
+    let local_addr = server.local_addr();
+    info!("stand-in remote signer listening {:?}", local_addr);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            error!("stand-in remote signer error: {:?}", e);
+        }
+    });
+    Ok(local_addr)
+}
+
+impl StandIn {
+    async fn handle(&self, req: Request<Body>) -> Response<Body> {
+        let (parts, body) = req.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, e.to_string()),
+        };
+        let result = match (parts.method, parts.uri.path()) {
+            (Method::GET, "/public_key") => json_response(&PublicKeyReply {
+                address: Signer::address(&self.wallet),
+                pk_g1: encode_point(&self.bls_signer.public_key_g1()),
+                pk_g2: encode_point(&self.bls_signer.public_key_g2()),
+            }),
+            (Method::POST, "/sign_registration") => self.sign_registration(&body).await,
+            (Method::POST, "/sign_blob") => self.sign_blob(&body).await,
+            (Method::POST, "/sign_transaction") => self.sign_transaction(&body).await,
+            _ => return error_response(StatusCode::NOT_FOUND, "not found".to_string()),
+        };
+        match result {
+            Ok(resp) => resp,
+            Err(e) if e.downcast_ref::<SlashingProtectionError>().is_some() => {
+                error_response(StatusCode::CONFLICT, e.to_string())
+            }
+            Err(e) => error_response(StatusCode::BAD_REQUEST, e.to_string()),
+        }
+    }
+
+    async fn sign_registration(&self, body: &[u8]) -> Result<Response<Body>> {
+        let request: SignRegistrationRequest = parse(body)?;
+        let signature = self
+            .bls_signer
+            .sign_registration(decode_point(&request.hash)?)
+            .await?;
+        json_response(&SignatureReply {
+            signature: encode_point(&signature),
+        })
+    }
+
+    async fn sign_blob(&self, body: &[u8]) -> Result<Response<Body>> {
+        let request: BlobSignRequest = parse(body)?;
+        let signature = self.bls_signer.sign_blob(&request).await?;
+        json_response(&SignatureReply {
+            signature: encode_point(&signature),
+        })
+    }
+
+    async fn sign_transaction(&self, body: &[u8]) -> Result<Response<Body>> {
+        let request: SignTransactionRequest = parse(body)?;
+        let signature = EthSigner::sign_transaction(&self.wallet, &request.tx).await?;
+        json_response(&TransactionSignatureReply { signature })
+    }
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|e| anyhow!("invalid request: {:?}", e))
+}
+
+fn json_response<T: Serialize>(reply: &T) -> Result<Response<Body>> {
+    Ok(Response::new(Body::from(serde_json::to_vec(reply)?)))
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Body> {
+    let mut resp = Response::new(Body::from(message));
+    *resp.status_mut() = status;
+    resp
+}
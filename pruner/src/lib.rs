@@ -4,17 +4,18 @@ extern crate tracing;
 use anyhow::Result;
 use chain_state::ChainState;
 use std::{sync::Arc, time::Duration};
+use storage::blob_status_db::BlobStatusDB;
 use storage::misc_db::MiscDB;
 use storage::slice_db::SliceDB;
 use storage::Storage;
-use tokio::{sync::RwLock, time::sleep};
+use tokio::time::sleep;
 
-pub async fn run_pruner(db: Arc<RwLock<Storage>>, chain_state: Arc<ChainState>) -> Result<()> {
-    let maybe_progress = db.read().await.get_prune_progress().await?;
+pub async fn run_pruner(db: Arc<Storage>, chain_state: Arc<ChainState>) -> Result<()> {
+    let maybe_progress = db.get_prune_progress().await?;
     match maybe_progress {
         Some(_) => {}
         None => {
-            db.write().await.put_prune_progress(0).await?;
+            db.put_prune_progress(0).await?;
         }
     }
     loop {
@@ -30,7 +31,7 @@ pub async fn run_pruner(db: Arc<RwLock<Storage>>, chain_state: Arc<ChainState>)
     }
 }
 
-async fn prune(db: Arc<RwLock<Storage>>, chain_state: Arc<ChainState>) -> Result<u64> {
+async fn prune(db: Arc<Storage>, chain_state: Arc<ChainState>) -> Result<u64> {
     let epoch = chain_state.da_signers.epoch_number().call().await?.as_u64();
     let epoch_window_size = chain_state
         .da_entrance
@@ -38,12 +39,57 @@ async fn prune(db: Arc<RwLock<Storage>>, chain_state: Arc<ChainState>) -> Result
         .call()
         .await?
         .as_u64();
-    let mut pruned = db.read().await.get_prune_progress().await?.unwrap();
+    prune_before_window(&db, epoch, epoch_window_size).await
+}
+
+/// Prunes the epochs before the window of `epoch_window_size` epochs ending at `epoch`,
+/// returning the last pruned epoch.
+async fn prune_before_window(db: &Storage, epoch: u64, epoch_window_size: u64) -> Result<u64> {
+    let mut pruned = db.get_prune_progress().await?.unwrap();
     while pruned + 1 + epoch_window_size < epoch {
-        db.write().await.prune(pruned + 1).await?;
-        db.write().await.put_prune_progress(pruned + 1).await?;
+        db.prune(pruned + 1).await?;
+        db.prune_erasure_commitments(pruned + 1).await?;
+        db.put_prune_progress(pruned + 1).await?;
         pruned += 1;
     }
 
     Ok(pruned)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn prune_before_window_test() {
+        let db = Storage::in_memory();
+        db.put_prune_progress(0).await.unwrap();
+        for epoch in 1..=5 {
+            db.put_slice(epoch, 0, [1; 32], vec![]).await.unwrap();
+            db.put_erasure_commitment(epoch, 0, [1; 32], vec![1])
+                .await
+                .unwrap();
+        }
+
+        // epochs 3 and 4 are in the window before epoch 5
+        assert_eq!(prune_before_window(&db, 5, 2).await.unwrap(), 2);
+        assert_eq!(db.get_prune_progress().await.unwrap(), Some(2));
+        assert_eq!(db.get_epoch_range().await.unwrap(), Some((3, 5)));
+        for epoch in 1..=5 {
+            assert_eq!(
+                db.get_epoch_info(epoch).await.unwrap().len(),
+                (epoch > 2) as usize
+            );
+            assert_eq!(
+                db.get_erasure_commitment(epoch, 0, [1; 32])
+                    .await
+                    .unwrap()
+                    .is_some(),
+                epoch > 2
+            );
+        }
+        // nothing more to prune until the epoch moves on
+        assert_eq!(prune_before_window(&db, 5, 2).await.unwrap(), 2);
+        assert_eq!(prune_before_window(&db, 6, 2).await.unwrap(), 3);
+    }
+}
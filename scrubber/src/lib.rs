@@ -0,0 +1,175 @@
+#[macro_use]
+extern crate tracing;
+
+use anyhow::Result;
+use std::{sync::Arc, time::Duration};
+use storage::slice_db::{SliceCheck, SliceDB};
+use storage::Storage;
+use tokio::time::sleep;
+use utils::{line::verify_line, metrics};
+
+#[derive(Debug, Clone)]
+pub struct ScrubberConfig {
+    // pause between two passes over the stored slices
+    pub interval: Duration,
+    // slices read per second, to stay behind signing and mining
+    pub slices_per_second: u64,
+    // move corrupted slices out of reach of reads instead of only reporting them
+    pub quarantine: bool,
+}
+
+/// What reading a slice back found.
+#[derive(Debug, PartialEq, Eq)]
+enum Finding {
+    Intact,
+    Missing,
+    Corrupted(String),
+    ProofMismatch { checksummed: bool },
+}
+
+impl Finding {
+    fn of(check: SliceCheck, index: usize) -> Self {
+        match check {
+            SliceCheck::Missing => Finding::Missing,
+            SliceCheck::Corrupted(reason) => Finding::Corrupted(reason),
+            SliceCheck::Intact {
+                slice,
+                row,
+                checksummed,
+            } => {
+                if verify_line(&row, index, &slice.merkle_proof, &slice.merkle_root) {
+                    Finding::Intact
+                } else {
+                    Finding::ProofMismatch { checksummed }
+                }
+            }
+        }
+    }
+
+    // the reason to report the slice corrupted, and whether to quarantine it
+    fn verdict(self) -> Option<(String, bool)> {
+        match self {
+            Finding::Intact | Finding::Missing => None,
+            Finding::Corrupted(reason) => Some((reason, true)),
+            // rows that mismatch their proof but not their checksum were stored that way, so
+            // quarantining them would not recover anything
+            Finding::ProofMismatch { checksummed } => {
+                Some(("merkle proof mismatch".to_string(), !checksummed))
+            }
+        }
+    }
+}
+
+// pause after each slice to read `slices_per_second` at most
+fn scrub_pause(slices_per_second: u64) -> Duration {
+    Duration::from_secs(1) / slices_per_second.clamp(1, u32::MAX as u64) as u32
+}
+
+pub async fn run_scrubber(db: Arc<Storage>, config: ScrubberConfig) -> Result<()> {
+    loop {
+        match scrub(&db, &config).await {
+            Ok((scrubbed, corrupted)) => {
+                info!("scrubbed {:?} slices, {:?} corrupted.", scrubbed, corrupted);
+            }
+            Err(e) => {
+                error!("failed to scrub slices, e={:?}", e);
+            }
+        }
+        sleep(config.interval).await;
+    }
+}
+
+async fn scrub(db: &Storage, config: &ScrubberConfig) -> Result<(u64, u64)> {
+    let (first_epoch, last_epoch) = match db.get_epoch_range().await? {
+        Some(range) => range,
+        None => return Ok((0, 0)),
+    };
+    let pause = scrub_pause(config.slices_per_second);
+    let mut scrubbed = 0;
+    let mut corrupted = 0;
+    for epoch in first_epoch..=last_epoch {
+        // empty once the epoch is pruned
+        for blob in db.get_epoch_info(epoch).await? {
+            for index in blob.indicies {
+                let index = index as usize;
+                let check = db
+                    .check_slice(epoch, blob.quorum_id, blob.storage_root, index)
+                    .await?;
+                scrubbed += 1;
+                metrics::SCRUBBED_SLICES.inc();
+                if let Some((reason, quarantine)) = Finding::of(check, index).verdict() {
+                    corrupted += 1;
+                    metrics::CORRUPTED_SLICES.inc();
+                    warn!(
+                        "corrupted slice {:?} of root {:?} in epoch {:?} quorum {:?}: {}",
+                        index,
+                        hex::encode(blob.storage_root),
+                        epoch,
+                        blob.quorum_id,
+                        reason
+                    );
+                    if quarantine && config.quarantine {
+                        db.quarantine_slice(epoch, blob.quorum_id, blob.storage_root, index)
+                            .await?;
+                    }
+                }
+                sleep(pause).await;
+            }
+        }
+    }
+    Ok((scrubbed, corrupted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(quarantine: bool) -> ScrubberConfig {
+        ScrubberConfig {
+            interval: Duration::from_secs(1),
+            slices_per_second: 1000,
+            quarantine,
+        }
+    }
+
+    #[test]
+    fn verdict_test() {
+        assert_eq!(Finding::Intact.verdict(), None);
+        // assigned slices which were never stored are not corrupted
+        assert_eq!(Finding::Missing.verdict(), None);
+        assert_eq!(
+            Finding::Corrupted("checksum mismatch".into()).verdict(),
+            Some(("checksum mismatch".to_string(), true))
+        );
+        // only rows which the checksum cannot vouch for are moved aside
+        assert_eq!(
+            Finding::ProofMismatch { checksummed: false }.verdict(),
+            Some(("merkle proof mismatch".to_string(), true))
+        );
+        assert_eq!(
+            Finding::ProofMismatch { checksummed: true }.verdict(),
+            Some(("merkle proof mismatch".to_string(), false))
+        );
+        assert_eq!(
+            Finding::of(SliceCheck::Corrupted("row data missing".into()), 0),
+            Finding::Corrupted("row data missing".into())
+        );
+        assert_eq!(Finding::of(SliceCheck::Missing, 0), Finding::Missing);
+    }
+
+    #[test]
+    fn scrub_pause_test() {
+        assert_eq!(scrub_pause(100), Duration::from_millis(10));
+        assert_eq!(scrub_pause(0), Duration::from_secs(1));
+        assert_eq!(scrub_pause(u64::MAX), Duration::from_nanos(0));
+    }
+
+    #[tokio::test]
+    async fn scrub_test() {
+        let db = Storage::in_memory();
+        assert_eq!(scrub(&db, &config(true)).await.unwrap(), (0, 0));
+        // quarantining slices of epochs without a partition does nothing
+        db.quarantine_slice(1, 0, [1; 32], 0).await.unwrap();
+        assert_eq!(scrub(&db, &config(false)).await.unwrap(), (0, 0));
+    }
+}
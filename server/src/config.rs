@@ -1,56 +1,170 @@
-use std::str::FromStr;
+use std::{str::FromStr, time::Duration};
 
 use anyhow::{anyhow, bail, Result};
 use ark_bn254::Fr;
 
+use crate::db_tool::DbCommand;
+use crate::snapshot::SnapshotConfig;
+use chain_utils::gas::{GasConfig, GasStrategy};
+use clap::ArgMatches;
 use config::ConfigError::NotFound;
 use ethers::{
     abi::Address,
-    types::{H160, H256},
+    types::{H160, H256, U256},
 };
+use scrubber::ScrubberConfig;
+use storage::{ColumnConfig, StorageConfig};
+use utils::{
+    hash_to_curve::G1HashScheme,
+    keystore::{decrypt_bls_key, decrypt_eth_key, read_keystore_password},
+};
+
+// a pass over the stored slices a day at 100 slices per second
+const DEFAULT_SLICE_SCRUB_INTERVAL: u64 = 86400;
+const DEFAULT_SLICE_SCRUB_RATE: u64 = 100;
+const DEFAULT_SNAPSHOTS_KEPT: u64 = 3;
+const DEFAULT_STORAGE_METRICS_INTERVAL: u64 = 60;
 
 mod cli {
     use clap::{arg, command, Command};
+    use ethers::types::H256;
+    use std::str::FromStr;
 
     pub fn cli_app<'a>() -> Command<'a> {
         command!()
             .arg(arg!(-c --config <FILE> "Sets a custom config file"))
-            .allow_external_subcommands(true)
+            .subcommand(
+                Command::new("migrate-db")
+                    .about("Migrates the database to the current schema and exits"),
+            )
+            .subcommand(
+                Command::new("restore-db")
+                    .about(
+                        "Validates a database snapshot, copies it to the empty data path and exits",
+                    )
+                    .arg(arg!(<SNAPSHOT> "Directory of the snapshot")),
+            )
+            .subcommand(
+                Command::new("export-metadata")
+                    .about("Exports blob statuses, quorums and sync progress to a file and exits")
+                    .arg(arg!(<FILE> "File to write the metadata to"))
+                    .arg(
+                        arg!(--db <DIR> "Database to export instead of the data path")
+                            .required(false),
+                    ),
+            )
+            .subcommand(
+                Command::new("db")
+                    .about("Inspects the database read-only, also while the node runs, and exits")
+                    .arg(
+                        arg!(--db <DIR> "Database to inspect instead of the data path")
+                            .required(false),
+                    )
+                    .subcommand_required(true)
+                    .subcommand(
+                        Command::new("progress").about("Prints the sync and prune progress"),
+                    )
+                    .subcommand(Command::new("epochs").about("Lists the epochs with stored slices"))
+                    .subcommand(
+                        Command::new("blobs")
+                            .about("Lists the blobs of an epoch with their stored slices")
+                            .arg(arg!(<EPOCH>).validator(|x| x.parse::<u64>())),
+                    )
+                    .subcommand(
+                        Command::new("blob-status")
+                            .about("Prints the status of the blobs with a storage root")
+                            .arg(arg!(<ROOT>).validator(H256::from_str)),
+                    )
+                    .subcommand(
+                        Command::new("quorums")
+                            .about("Prints the slices assigned in the quorums of an epoch")
+                            .arg(arg!(<EPOCH>).validator(|x| x.parse::<u64>()))
+                            .arg(arg!([QUORUM_ID]).validator(|x| x.parse::<u64>())),
+                    )
+                    .subcommand(
+                        Command::new("slice")
+                            .about("Reads, checks and decodes a stored slice")
+                            .arg(arg!(<EPOCH>).validator(|x| x.parse::<u64>()))
+                            .arg(arg!(<QUORUM_ID>).validator(|x| x.parse::<u64>()))
+                            .arg(arg!(<ROOT>).validator(H256::from_str))
+                            .arg(arg!(<INDEX>).validator(|x| x.parse::<usize>())),
+                    )
+                    .subcommand(
+                        Command::new("stats")
+                            .about("Scans the key counts and sizes of each column"),
+                    ),
+            )
     }
 }
 
-struct RawConfig(config::Config);
+pub struct RawConfig(config::Config);
 
 impl RawConfig {
-    fn get_string(&self, key: &'static str) -> Result<String> {
+    fn get_string(&self, key: &str) -> Result<String> {
         self.0
             .get_string(key)
             .map_err(|e| anyhow!("Cannot parse config key `{}` as string: {:?}", key, e))
     }
 
-    fn get_u64(&self, key: &'static str) -> Result<u64> {
+    fn get_u64(&self, key: &str) -> Result<u64> {
         self.0
             .get_int(key)
             .map(|x| x as u64)
             .map_err(|e| anyhow!("Cannot parse config key `{}` as int: {:?}", key, e))
     }
 
-    fn get_address(&self, key: &'static str) -> Result<Address> {
+    fn get_address(&self, key: &str) -> Result<Address> {
         Address::from_str(&self.get_string(key)?)
             .map_err(|err| anyhow!("Cannot parse config key `{}` as address: {:?}", key, err))
     }
 
-    fn get_bytes32(&self, key: &'static str) -> Result<H256> {
+    fn get_address_opt(&self, key: &str) -> Result<Option<Address>> {
+        match self.0.get_string(key) {
+            Ok(x) => Address::from_str(&x)
+                .map(Some)
+                .map_err(|err| anyhow!("Cannot parse config key `{}` as address: {:?}", key, err)),
+            Err(NotFound(_)) => Ok(None),
+            Err(e) => Err(anyhow!(
+                "Cannot parse config key `{}` as string: {:?}",
+                key,
+                e
+            )),
+        }
+    }
+
+    fn get_bytes32(&self, key: &str) -> Result<H256> {
         H256::from_str(&self.get_string(key)?)
             .map_err(|err| anyhow!("Cannot parse config key `{}` as bytes32: {:?}", key, err))
     }
 
-    fn get_bls_key(&self, key: &'static str) -> Result<Fr> {
+    fn get_bls_key(&self, key: &str) -> Result<Fr> {
         Fr::from_str(&self.get_string(key)?)
             .map_err(|err| anyhow!("Cannot parse config key `{}` as bls key: {:?}", key, err))
     }
 
-    fn get_u64_opt(&self, key: &'static str) -> Result<Option<u64>> {
+    // the plaintext key, or the key decrypted from `<key>_keystore` if that is set instead
+    fn get_eth_key(&self, key: &str) -> Result<Option<H256>> {
+        match self.get_string_opt(&format!("{}_keystore", key))? {
+            Some(path) => Ok(Some(decrypt_eth_key(path, &self.keystore_password()?)?)),
+            None => match self.get_string_opt(key)? {
+                Some(_) => Ok(Some(self.get_bytes32(key)?)),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn get_bls_key_or_keystore(&self, key: &str) -> Result<Fr> {
+        match self.get_string_opt(&format!("{}_keystore", key))? {
+            Some(path) => decrypt_bls_key(path, &self.keystore_password()?),
+            None => self.get_bls_key(key),
+        }
+    }
+
+    fn keystore_password(&self) -> Result<String> {
+        read_keystore_password(self.get_string_opt("keystore_password_file")?.as_deref())
+    }
+
+    fn get_u64_opt(&self, key: &str) -> Result<Option<u64>> {
         match self.0.get_int(key) {
             Ok(x) => Ok(Some(x as u64)),
             Err(NotFound(_)) => Ok(None),
@@ -58,7 +172,56 @@ impl RawConfig {
         }
     }
 
-    fn get_bool_opt(&self, key: &'static str) -> Result<bool> {
+    fn get_wei(&self, key: &str) -> Result<U256> {
+        self.get_wei_opt(key)?
+            .ok_or_else(|| anyhow!("Config key `{}` missing", key))
+    }
+
+    // amounts of wei may exceed 64 bits, so they are parsed from decimal strings (or integers)
+    fn get_wei_opt(&self, key: &str) -> Result<Option<U256>> {
+        match self.get_string_opt(key)? {
+            Some(x) => U256::from_dec_str(x.trim())
+                .map(Some)
+                .map_err(|e| anyhow!("Cannot parse config key `{}` as wei: {:?}", key, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_string_opt(&self, key: &str) -> Result<Option<String>> {
+        match self.0.get_string(key) {
+            Ok(x) => Ok(Some(x)),
+            Err(NotFound(_)) => Ok(None),
+            Err(e) => Err(anyhow!(
+                "Cannot parse config key `{}` as string: {:?}",
+                key,
+                e
+            )),
+        }
+    }
+
+    fn get_gas_config(&self, account: &str) -> Result<GasConfig> {
+        let strategy = match self
+            .get_string_opt(&format!("{}_gas_strategy", account))?
+            .as_deref()
+        {
+            None | Some("legacy") => GasStrategy::Legacy,
+            Some("fixed") => GasStrategy::Fixed(self.get_wei(&format!("{}_gas_price", account))?),
+            Some("oracle") => GasStrategy::OracleMultiplier(
+                self.get_u64(&format!("{}_gas_price_percent", account))?,
+            ),
+            Some("eip1559") => GasStrategy::Eip1559 {
+                max_priority_fee_per_gas: self
+                    .get_wei_opt(&format!("{}_max_priority_fee_per_gas", account))?,
+            },
+            Some(x) => bail!(anyhow!("Unknown gas strategy `{}` of {}", x, account)),
+        };
+        Ok(GasConfig {
+            strategy,
+            max_tx_cost: self.get_wei_opt(&format!("{}_max_tx_cost", account))?,
+        })
+    }
+
+    fn get_bool_opt(&self, key: &str) -> Result<bool> {
         match self.0.get_bool(key) {
             Ok(x) => Ok(x),
             Err(NotFound(_)) => Ok(false),
@@ -81,29 +244,211 @@ pub struct Config {
     pub eth_rpc_url: String,
     pub start_block_number: u64,
     pub da_entrance_address: H160,
-    pub signer_bls_private_key: Fr,
-    pub signer_eth_private_key: H256,
+    pub multicall_address: Option<H160>,
+    // keys are held by the remote signer instead if `remote_signer_url` is set
+    pub remote_signer_url: Option<String>,
+    pub signer_bls_private_key: Option<Fr>,
+    // next BLS key, used to sign from `bls_key_rotation_epoch` on once registered on chain, held
+    // by the remote signer at `remote_signer_next_url` instead if `remote_signer_url` is set
+    pub signer_next_bls_private_key: Option<Fr>,
+    pub remote_signer_next_url: Option<String>,
+    pub bls_key_rotation_epoch: Option<u64>,
+    pub signer_eth_private_key: Option<H256>,
+    // how messages are hashed to G1 for the DA contracts of the deployment
+    pub hash_scheme: G1HashScheme,
     pub miner_eth_private_key: H256,
     pub data_path: String,
+    pub storage: StorageConfig,
+    pub scrubber: ScrubberConfig,
+    // pause between two exports of storage statistics to metrics
+    pub storage_metrics_interval: Duration,
+    // database snapshots are disabled if unset
+    pub snapshot: Option<SnapshotConfig>,
+    // metadata exported by another node, imported before syncing DA events from scratch
+    pub bootstrap_metadata_file: Option<String>,
     pub enable_das: bool,
     pub das_test: bool,
     pub prometheus_exporter_address: String,
+    pub registration_alert_webhook: Option<String>,
+    pub registration_alert_blocks: Option<u64>,
+    pub transaction_confirmation_timeout: Option<u64>,
+    pub max_fee_per_gas: Option<U256>,
+    pub signer_gas: GasConfig,
+    pub miner_gas: GasConfig,
+    pub low_balance_threshold: Option<U256>,
+}
+
+/// The command line and the config file it names, parsed once and dispatched on by subcommand.
+pub fn raw_config_from_cli() -> Result<(ArgMatches, RawConfig)> {
+    let matches = cli::cli_app().get_matches();
+    let c = if let Some(config_file) = matches.value_of("config") {
+        RawConfig(
+            config::Config::builder()
+                .add_source(config::File::with_name(config_file))
+                .build()?,
+        )
+    } else {
+        bail!(anyhow!("Config file missing!"));
+    };
+    Ok((matches, c))
+}
+
+fn storage_config(c: &RawConfig) -> Result<StorageConfig> {
+    Ok(StorageConfig {
+        // refuse to start on an older database schema instead of migrating it
+        migrate: !c.get_bool_opt("db_manual_migration")?,
+        blob_index: column_config(c, "blob_index")?,
+        slice: column_config(c, "slice")?,
+        slice_data: column_config(c, "slice_data")?,
+        epochs_per_partition: c.get_u64_opt("db_epochs_per_partition")?,
+    })
+}
+
+fn column_config(c: &RawConfig, column: &str) -> Result<ColumnConfig> {
+    Ok(ColumnConfig {
+        memory_budget: c
+            .get_u64_opt(&format!("db_{}_memory_budget", column))?
+            .map(|x| x as usize),
+        block_cache: c
+            .get_u64_opt(&format!("db_{}_block_cache", column))?
+            .map(|x| x as usize),
+        compression: c
+            .get_string_opt(&format!("db_{}_compression", column))?
+            .map(|x| x.parse())
+            .transpose()?,
+        bloom_filter_bits: c
+            .get_u64_opt(&format!("db_{}_bloom_filter_bits", column))?
+            .map(|x| x as u32),
+    })
+}
+
+/// The data path and storage config of the `migrate-db` command.
+pub fn migrate_db_args(c: &RawConfig) -> Result<(String, StorageConfig)> {
+    Ok((c.get_string("data_path")?, storage_config(c)?))
+}
+
+/// The snapshot directory and data path of the `restore-db` command.
+pub fn restore_db_args(c: &RawConfig, matches: &ArgMatches) -> Result<(String, String)> {
+    Ok((
+        matches.value_of("SNAPSHOT").unwrap().to_string(),
+        c.get_string("data_path")?,
+    ))
+}
+
+// the database given with `--db`, or the data path
+fn db_path(c: &RawConfig, matches: &ArgMatches) -> Result<String> {
+    match matches.value_of("db") {
+        Some(db) => Ok(db.to_string()),
+        None => c.get_string("data_path"),
+    }
+}
+
+/// The database path, storage config and target file of the `export-metadata` command.
+pub fn export_metadata_args(
+    c: &RawConfig,
+    matches: &ArgMatches,
+) -> Result<(String, StorageConfig, String)> {
+    Ok((
+        db_path(c, matches)?,
+        storage_config(c)?,
+        matches.value_of("FILE").unwrap().to_string(),
+    ))
+}
+
+fn parse_arg<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T>
+where
+    T::Err: std::fmt::Debug,
+{
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|e| anyhow!("Cannot parse {} `{}`: {:?}", name, value, e))
+}
+
+fn db_subcommand(matches: &ArgMatches) -> Result<DbCommand> {
+    Ok(match matches.subcommand() {
+        Some(("progress", _)) => DbCommand::Progress,
+        Some(("epochs", _)) => DbCommand::Epochs,
+        Some(("blobs", m)) => DbCommand::Blobs {
+            epoch: parse_arg(m, "EPOCH")?,
+        },
+        Some(("blob-status", m)) => DbCommand::BlobStatus {
+            storage_root: parse_arg(m, "ROOT")?,
+        },
+        Some(("quorums", m)) => DbCommand::Quorums {
+            epoch: parse_arg(m, "EPOCH")?,
+            quorum_id: match m.value_of("QUORUM_ID") {
+                Some(_) => Some(parse_arg(m, "QUORUM_ID")?),
+                None => None,
+            },
+        },
+        Some(("slice", m)) => DbCommand::Slice {
+            epoch: parse_arg(m, "EPOCH")?,
+            quorum_id: parse_arg(m, "QUORUM_ID")?,
+            storage_root: parse_arg(m, "ROOT")?,
+            index: parse_arg(m, "INDEX")?,
+        },
+        Some(("stats", _)) => DbCommand::Stats,
+        _ => bail!("unknown db command"),
+    })
+}
+
+/// The database path, storage config and inspection of the `db` command.
+pub fn db_args(c: &RawConfig, matches: &ArgMatches) -> Result<(String, StorageConfig, DbCommand)> {
+    Ok((
+        db_path(c, matches)?,
+        storage_config(c)?,
+        db_subcommand(matches)?,
+    ))
 }
 
 impl Config {
-    pub fn from_cli_file() -> Result<Self> {
-        let matches = cli::cli_app().get_matches();
-        let c = if let Some(config_file) = matches.value_of("config") {
-            RawConfig(
-                config::Config::builder()
-                    .add_source(config::File::with_name(config_file))
-                    .build()?,
-            )
+    pub fn from_raw(c: &RawConfig) -> Result<Self> {
+        let enable_das = c.get_bool_opt("enable_das")?;
+        let remote_signer_url = c.get_string_opt("remote_signer_url")?;
+        let (signer_bls_private_key, signer_eth_private_key) = if remote_signer_url.is_some() {
+            (None, None)
         } else {
-            bail!(anyhow!("Config file missing!"));
+            (
+                Some(c.get_bls_key_or_keystore("signer_bls_private_key")?),
+                Some(
+                    c.get_eth_key("signer_eth_private_key")?
+                        .ok_or_else(|| anyhow!("Config key `signer_eth_private_key` missing"))?,
+                ),
+            )
         };
 
-        let enable_das = c.get_bool_opt("enable_das")?;
+        let bls_key_rotation_epoch = c.get_u64_opt("bls_key_rotation_epoch")?;
+        let (signer_next_bls_private_key, remote_signer_next_url) =
+            match (bls_key_rotation_epoch, &remote_signer_url) {
+                (None, _) => (None, None),
+                (Some(_), None) => (
+                    Some(c.get_bls_key_or_keystore("signer_next_bls_private_key")?),
+                    None,
+                ),
+                (Some(_), Some(_)) => {
+                    (
+                        None,
+                        Some(c.get_string_opt("remote_signer_next_url")?.ok_or_else(|| {
+                            anyhow!("Config key `remote_signer_next_url` missing")
+                        })?),
+                    )
+                }
+            };
+
+        // a missing or invalid plaintext miner key, like the empty one of the example config,
+        // falls back to the signer key, while keystore errors are reported
+        let miner_eth_private_key = if enable_das {
+            let miner_key = match c.get_string_opt("miner_eth_private_key_keystore")? {
+                Some(_) => c.get_eth_key("miner_eth_private_key")?,
+                None => c.get_bytes32("miner_eth_private_key").ok(),
+            };
+            miner_key
+                .or(signer_eth_private_key)
+                .ok_or_else(|| anyhow!("Config key `miner_eth_private_key` missing"))?
+        } else {
+            H256::zero()
+        };
 
         Ok(Self {
             enable_das: c.get_bool_opt("enable_das")?,
@@ -117,16 +462,104 @@ impl Config {
             eth_rpc_url: c.get_string("eth_rpc_endpoint")?,
             start_block_number: c.get_u64("start_block_number")?,
             da_entrance_address: c.get_address("da_entrance_address")?,
-            signer_bls_private_key: c.get_bls_key("signer_bls_private_key")?,
-            signer_eth_private_key: c.get_bytes32("signer_eth_private_key")?,
-            miner_eth_private_key: if enable_das {
-                c.get_bytes32("miner_eth_private_key")
-                    .or_else(|_| c.get_bytes32("signer_eth_private_key"))?
-            } else {
-                H256::zero()
+            multicall_address: c.get_address_opt("multicall_address")?,
+            remote_signer_url,
+            signer_bls_private_key,
+            signer_next_bls_private_key,
+            remote_signer_next_url,
+            bls_key_rotation_epoch,
+            signer_eth_private_key,
+            hash_scheme: match c.get_string_opt("hash_to_g1_scheme")? {
+                Some(x) => x.parse()?,
+                None => G1HashScheme::default(),
             },
+            miner_eth_private_key,
             data_path: c.get_string("data_path")?,
+            storage: storage_config(c)?,
+            scrubber: ScrubberConfig {
+                interval: Duration::from_secs(
+                    c.get_u64_opt("slice_scrub_interval")?
+                        .unwrap_or(DEFAULT_SLICE_SCRUB_INTERVAL),
+                ),
+                slices_per_second: c
+                    .get_u64_opt("slice_scrub_rate")?
+                    .unwrap_or(DEFAULT_SLICE_SCRUB_RATE),
+                quarantine: c.get_bool_opt("slice_scrub_quarantine")?,
+            },
+            storage_metrics_interval: Duration::from_secs(
+                c.get_u64_opt("db_metrics_interval")?
+                    .unwrap_or(DEFAULT_STORAGE_METRICS_INTERVAL),
+            ),
+            snapshot: match c.get_string_opt("db_snapshot_dir")? {
+                Some(dir) => Some(SnapshotConfig {
+                    dir: dir.into(),
+                    interval: c
+                        .get_u64_opt("db_snapshot_interval")?
+                        .map(Duration::from_secs),
+                    keep: c
+                        .get_u64_opt("db_snapshot_keep")?
+                        .unwrap_or(DEFAULT_SNAPSHOTS_KEPT) as usize,
+                }),
+                None => None,
+            },
+            bootstrap_metadata_file: c.get_string_opt("bootstrap_metadata_file")?,
             prometheus_exporter_address: c.get_string("prometheus_exporter_address")?,
+            registration_alert_webhook: c.get_string_opt("registration_alert_webhook")?,
+            registration_alert_blocks: c.get_u64_opt("registration_alert_blocks")?,
+            transaction_confirmation_timeout: c.get_u64_opt("transaction_confirmation_timeout")?,
+            max_fee_per_gas: c.get_wei_opt("max_fee_per_gas")?,
+            signer_gas: c.get_gas_config("signer")?,
+            miner_gas: c.get_gas_config("miner")?,
+            low_balance_threshold: c.get_wei_opt("low_balance_threshold")?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_config(toml: &str) -> RawConfig {
+        RawConfig(
+            config::Config::builder()
+                .add_source(config::File::from_str(toml, config::FileFormat::Toml))
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn get_wei_opt_test() {
+        let c = raw_config(
+            r#"
+            int = 1000000000000000000
+            string = "100000000000000000000"
+            negative = -1
+            overflow = "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            "#,
+        );
+        assert_eq!(c.get_wei_opt("int").unwrap(), Some(U256::exp10(18)));
+        assert_eq!(c.get_wei_opt("string").unwrap(), Some(U256::exp10(20)));
+        assert_eq!(c.get_wei_opt("missing").unwrap(), None);
+        assert!(c.get_wei_opt("negative").is_err());
+        assert!(c.get_wei_opt("overflow").is_err());
+        assert!(c.get_wei("missing").is_err());
+    }
+
+    #[test]
+    fn get_gas_config_test() {
+        let c = raw_config(
+            r#"
+            signer_gas_strategy = "fixed"
+            signer_gas_price = "20000000000000000000"
+            signer_max_tx_cost = 10000000000000000
+            miner_gas_strategy = "eip1559"
+            miner_max_priority_fee_per_gas = -2
+            "#,
+        );
+        let gas = c.get_gas_config("signer").unwrap();
+        assert!(matches!(gas.strategy, GasStrategy::Fixed(x) if x == U256::exp10(19) * 2));
+        assert_eq!(gas.max_tx_cost, Some(U256::exp10(16)));
+        assert!(c.get_gas_config("miner").is_err());
+    }
+}
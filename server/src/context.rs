@@ -1,35 +1,98 @@
-use anyhow::Result;
-use chain_state::transactor::Transactor;
-use chain_utils::DefaultMiddleware;
-use std::sync::Arc;
+use anyhow::{anyhow, bail, Result};
+use chain_state::transactor::{Transactor, TransactorConfig};
+use ethers::{
+    providers::Middleware,
+    signers::{LocalWallet, Signer},
+};
+use key_signer::{
+    BlsKeyRing, BlsSigner, EthSigner, LocalBlsSigner, ProtectedSigner, RemoteSigner,
+    StorageSigningHistory,
+};
+use std::{sync::Arc, time::Duration};
 use storage::Storage;
-use tokio::sync::{Mutex, RwLock};
+use tokio::sync::Mutex;
 
 use crate::config::Config;
 
 pub struct Context {
     pub config: Config,
     pub transactor: Arc<Mutex<Transactor>>,
-    pub db: Arc<RwLock<Storage>>,
-    pub provider: DefaultMiddleware,
+    pub db: Arc<Storage>,
+    pub bls_keys: Arc<BlsKeyRing>,
+    // the key ring behind slashing protection
+    pub bls_signer: Arc<dyn BlsSigner>,
 }
 
 impl Context {
     pub async fn new(config: Config) -> Result<Self> {
-        let provider =
-            chain_utils::make_provider(&config.eth_rpc_url, &config.signer_eth_private_key)
-                .await
-                .unwrap();
-        let transactor: Arc<Mutex<Transactor>> =
-            Arc::new(Mutex::new(Transactor::new(provider.clone()).unwrap()));
+        let provider = Arc::new(chain_utils::make_rpc_provider(&config.eth_rpc_url)?);
         // db
-        let db = Arc::new(RwLock::new(Storage::new(&config.data_path).unwrap()));
+        let db = Arc::new(Storage::new(&config.data_path, &config.storage)?);
+        // signers
+        let (eth_signer, bls_signer): (Arc<dyn EthSigner>, Arc<dyn BlsSigner>) = match &config
+            .remote_signer_url
+        {
+            Some(url) => {
+                let remote_signer = Arc::new(RemoteSigner::connect(url).await?);
+                (remote_signer.clone(), remote_signer)
+            }
+            None => {
+                let eth_private_key = config
+                    .signer_eth_private_key
+                    .ok_or_else(|| anyhow!("signer eth private key missing"))?;
+                let bls_private_key = config
+                    .signer_bls_private_key
+                    .ok_or_else(|| anyhow!("signer bls private key missing"))?;
+                let wallet = LocalWallet::from_bytes(&eth_private_key[..])
+                    .map_err(|e| anyhow!("Invalid signer private key: {:?}", e))?
+                    .with_chain_id(provider.get_chainid().await?.as_u64());
+                (
+                    Arc::new(wallet),
+                    Arc::new(
+                        LocalBlsSigner::new(bls_private_key).with_hash_scheme(config.hash_scheme),
+                    ),
+                )
+            }
+        };
+        let mut bls_keys = BlsKeyRing::new(bls_signer);
+        if let Some(rotation_epoch) = config.bls_key_rotation_epoch {
+            // the next key is held by the same backend as the current one
+            let next_signer: Arc<dyn BlsSigner> = match (
+                &config.remote_signer_next_url,
+                config.signer_next_bls_private_key,
+            ) {
+                (Some(url), _) => Arc::new(RemoteSigner::connect(url).await?),
+                (None, Some(next_key)) => {
+                    Arc::new(LocalBlsSigner::new(next_key).with_hash_scheme(config.hash_scheme))
+                }
+                (None, None) => bail!("next signer bls private key missing"),
+            };
+            bls_keys = bls_keys.with_next(next_signer, rotation_epoch);
+        }
+        let bls_keys = Arc::new(bls_keys);
+        let bls_signer = Arc::new(ProtectedSigner::new(
+            bls_keys.clone(),
+            Arc::new(StorageSigningHistory::new(db.clone())),
+        ));
+        let transactor_config = TransactorConfig {
+            confirmation_timeout: config
+                .transaction_confirmation_timeout
+                .map(Duration::from_secs),
+            max_fee_per_gas: config.max_fee_per_gas,
+            gas: config.signer_gas.clone(),
+        };
+        let transactor: Arc<Mutex<Transactor>> = Arc::new(Mutex::new(
+            Transactor::new(provider, eth_signer, db.clone(), transactor_config)
+                .await
+                .unwrap(),
+        ));
 
         Ok(Self {
             config,
             transactor,
             db,
-            provider,
+            bls_keys,
+            bls_signer,
         })
     }
 }
@@ -0,0 +1,144 @@
+use anyhow::Result;
+use ethers::types::H256;
+use storage::{
+    blob_status_db::{BlobStatus, BlobStatusDB},
+    misc_db::MiscDB,
+    quorum_db::QuorumDB,
+    slice_db::{SliceCheck, SliceDB},
+    Storage,
+};
+
+/// Inspections of the `db` command, printed to stdout.
+#[derive(Debug)]
+pub enum DbCommand {
+    Progress,
+    Epochs,
+    Blobs {
+        epoch: u64,
+    },
+    BlobStatus {
+        storage_root: H256,
+    },
+    Quorums {
+        epoch: u64,
+        quorum_id: Option<u64>,
+    },
+    Slice {
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: H256,
+        index: usize,
+    },
+    Stats,
+}
+
+fn status_name(status: BlobStatus) -> &'static str {
+    match status {
+        BlobStatus::UPLOADED => "uploaded",
+        BlobStatus::VERIFIED => "verified",
+    }
+}
+
+pub async fn run_db_command(db: &Storage, command: DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Progress => {
+            println!("sync progress: {:?}", db.get_sync_progress().await?);
+            println!("prune progress: {:?}", db.get_prune_progress().await?);
+        }
+        DbCommand::Epochs => match db.get_epoch_range().await? {
+            Some((first_epoch, last_epoch)) => {
+                for epoch in first_epoch..=last_epoch {
+                    let blobs = db.get_epoch_info(epoch).await?;
+                    if !blobs.is_empty() {
+                        println!("epoch {}: {} blobs", epoch, blobs.len());
+                    }
+                }
+            }
+            None => println!("no slices stored"),
+        },
+        DbCommand::Blobs { epoch } => {
+            for blob in db.get_epoch_info(epoch).await? {
+                println!(
+                    "quorum {} root {:?}: slices {:?}",
+                    blob.quorum_id,
+                    H256::from(blob.storage_root),
+                    blob.indicies
+                );
+            }
+        }
+        DbCommand::BlobStatus { storage_root } => {
+            let statuses = db.find_blob_statuses(storage_root.0).await?;
+            if statuses.is_empty() {
+                println!("blob not found");
+            }
+            for (epoch, quorum_id, status) in statuses {
+                println!(
+                    "epoch {} quorum {}: {}",
+                    epoch,
+                    quorum_id,
+                    status_name(status)
+                );
+            }
+        }
+        DbCommand::Quorums { epoch, quorum_id } => {
+            let quorum_ids = match (quorum_id, db.get_quorum_num(epoch).await?) {
+                (Some(quorum_id), _) => quorum_id..quorum_id + 1,
+                (None, Some(quorum_num)) => 0..quorum_num,
+                (None, None) => {
+                    println!("quorums of epoch {} not fetched", epoch);
+                    return Ok(());
+                }
+            };
+            for quorum_id in quorum_ids {
+                match db.get_assgined_slices(epoch, quorum_id).await? {
+                    Some(assigned) => {
+                        println!("quorum {}: assigned slices {:?}", quorum_id, assigned.0)
+                    }
+                    None => println!("quorum {}: not fetched", quorum_id),
+                }
+            }
+        }
+        DbCommand::Slice {
+            epoch,
+            quorum_id,
+            storage_root,
+            index,
+        } => match db
+            .check_slice(epoch, quorum_id, storage_root.0, index)
+            .await?
+        {
+            SliceCheck::Missing => println!("slice not found"),
+            SliceCheck::Corrupted(reason) => println!("slice corrupted: {}", reason),
+            SliceCheck::Intact {
+                slice,
+                row,
+                checksummed,
+            } => {
+                println!("index: {}", slice.index);
+                for (i, root) in slice.merkle_root.iter().enumerate() {
+                    println!("merkle root {}: {:?}", i, H256::from(*root));
+                }
+                println!("merkle proof: {} nodes", slice.merkle_proof.len());
+                for node in &slice.merkle_proof {
+                    println!("  {:?}", H256::from(*node));
+                }
+                println!("commitment: {:?}", slice.commitment);
+                println!("row: {} words", row.len());
+                println!("checksummed: {}", checksummed);
+            }
+        },
+        DbCommand::Stats => {
+            println!(
+                "{:<24} {:<20} {:>12} {:>16}",
+                "database", "column", "keys", "bytes"
+            );
+            for stats in db.column_stats()? {
+                println!(
+                    "{:<24} {:<20} {:>12} {:>16}",
+                    stats.database, stats.column, stats.keys, stats.bytes
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -3,19 +3,31 @@ extern crate tracing;
 
 mod config;
 mod context;
+mod db_tool;
 mod runtime;
+mod snapshot;
+mod storage_metrics;
 
 use std::{error::Error, net::SocketAddr, str::FromStr, sync::Arc};
 
 use anyhow::{anyhow, Result};
 
 use chain_state::{
-    da_handler::start_da_monitor, signers_handler::start_epoch_registration, ChainState,
+    balance_monitor::{start_balance_monitor, BalanceMonitorConfig},
+    da_handler::start_da_monitor,
+    key_check::start_key_check,
+    quorum_handler::start_quorum_prefetch,
+    registration_tracker::RegistrationAlertConfig,
+    signers_handler::start_epoch_registration,
+    ChainState,
 };
 use chain_utils::make_provider;
 use da_miner::DasMineService;
+use ethers::signers::{LocalWallet, Signer};
 use grpc::run_server;
 use pruner::run_pruner;
+use scrubber::run_scrubber;
+use storage::{Storage, SCHEMA_VERSION};
 
 use prometheus_exporter::Exporter;
 use runtime::Environment;
@@ -23,13 +35,19 @@ use task_executor::TaskExecutor;
 use tracing::Level;
 use tracing_subscriber::EnvFilter;
 
-use crate::config::Config;
+use crate::config::{
+    db_args, export_metadata_args, migrate_db_args, raw_config_from_cli, restore_db_args, Config,
+};
 use crate::context::Context;
+use crate::db_tool::run_db_command;
 use crate::runtime::make_environment;
+use crate::snapshot::start_snapshots;
+use crate::storage_metrics::start_storage_metrics;
 
 async fn start_grpc_server(chain_state: Arc<ChainState>, ctx: &Context) -> Result<()> {
     let db = ctx.db.clone();
-    let signer_bls_private_key = ctx.config.signer_bls_private_key;
+    let bls_signer = ctx.bls_signer.clone();
+    let bls_keys = ctx.bls_keys.clone();
     let grpc_listen_address = ctx.config.grpc_listen_address.clone();
     let encoder_params_dir = ctx.config.encoder_params_dir.clone();
     let max_ongoing_sign_request = ctx.config.max_ongoing_sign_request;
@@ -38,7 +56,8 @@ async fn start_grpc_server(chain_state: Arc<ChainState>, ctx: &Context) -> Resul
         run_server(
             db,
             chain_state,
-            signer_bls_private_key,
+            bls_signer,
+            bls_keys,
             SocketAddr::from_str(&grpc_listen_address).unwrap(),
             encoder_params_dir,
             max_ongoing_sign_request,
@@ -61,23 +80,58 @@ async fn start_pruner(chain_state: Arc<ChainState>, ctx: &Context) -> Result<()>
     Ok(())
 }
 
+fn start_scrubber(ctx: &Context) {
+    let db = ctx.db.clone();
+    let config = ctx.config.scrubber.clone();
+    tokio::spawn(async move {
+        run_scrubber(db, config)
+            .await
+            .map_err(|e| anyhow!(e.to_string()))
+            .unwrap();
+    });
+}
+
 async fn setup_chain_state(ctx: &Context) -> Result<Arc<ChainState>> {
     let chain_state = Arc::new(
         ChainState::new(
             &ctx.config.eth_rpc_url,
             ctx.config.da_entrance_address,
+            ctx.config.multicall_address,
             ctx.transactor.clone(),
             ctx.db.clone(),
+            ctx.config.hash_scheme,
         )
         .await?,
     );
     chain_state
-        .check_signer_registration(
-            ctx.config.signer_bls_private_key,
-            ctx.config.socket_address.clone(),
-        )
+        .check_signer_registration(&*ctx.bls_signer, ctx.config.socket_address.clone())
         .await?;
-    start_epoch_registration(chain_state.clone(), ctx.config.signer_bls_private_key);
+    chain_state.check_registered_key(&ctx.bls_keys).await?;
+    start_key_check(chain_state.clone(), ctx.bls_keys.clone());
+    start_quorum_prefetch(chain_state.clone());
+    start_epoch_registration(
+        chain_state.clone(),
+        ctx.bls_keys.clone(),
+        RegistrationAlertConfig {
+            webhook_url: ctx.config.registration_alert_webhook.clone(),
+            alert_blocks: ctx.config.registration_alert_blocks,
+        },
+    );
+    let miner_address = if ctx.config.enable_das {
+        Some(LocalWallet::from_bytes(&ctx.config.miner_eth_private_key[..])?.address())
+    } else {
+        None
+    };
+    start_balance_monitor(
+        chain_state.clone(),
+        BalanceMonitorConfig {
+            miner_address,
+            low_balance_threshold: ctx.config.low_balance_threshold,
+        },
+    );
+    if let Some(path) = &ctx.config.bootstrap_metadata_file {
+        chain_state.bootstrap_metadata(path).await?;
+    }
     start_da_monitor(chain_state.clone(), ctx.config.start_block_number).await?;
     Ok(chain_state)
 }
@@ -86,6 +140,11 @@ async fn start_server(ctx: &Context) -> Result<()> {
     let chain_state = setup_chain_state(ctx).await?;
     start_grpc_server(chain_state.clone(), ctx).await?;
     start_pruner(chain_state.clone(), ctx).await?;
+    start_scrubber(ctx);
+    start_storage_metrics(ctx.db.clone(), ctx.config.storage_metrics_interval);
+    if let Some(snapshot_config) = ctx.config.snapshot.clone() {
+        start_snapshots(ctx.db.clone(), snapshot_config)?;
+    }
     Ok(())
 }
 
@@ -102,6 +161,7 @@ async fn start_das_service(executor: TaskExecutor, ctx: &Context) {
         ctx.config.da_entrance_address,
         ctx.config.das_test,
         ctx.db.clone(),
+        ctx.config.miner_gas.clone(),
     )
     .await
     .unwrap();
@@ -140,7 +200,59 @@ async fn async_main(
     executor: TaskExecutor,
 ) -> Result<(), Box<dyn Error>> {
     // CLI, config
-    let config = Config::from_cli_file().unwrap();
+    let (matches, c) = raw_config_from_cli()?;
+    match matches.subcommand() {
+        Some(("migrate-db", _)) => {
+            let (data_path, storage_config) = migrate_db_args(&c)?;
+            tracing_subscriber::fmt().init();
+            let version = Storage::migrate(&data_path, &storage_config)?;
+            info!(
+                "database migrated from schema version {} to {}",
+                version, SCHEMA_VERSION
+            );
+            return Ok(());
+        }
+        Some(("restore-db", sub_matches)) => {
+            let (snapshot, data_path) = restore_db_args(&c, sub_matches)?;
+            tracing_subscriber::fmt().init();
+            let manifest = Storage::restore(&snapshot, &data_path)?;
+            info!(
+                "database restored to {:?} from snapshot {:?} taken at {:?}, sync progress {:?}, \
+                 prune progress {:?}, epochs {:?}",
+                data_path,
+                snapshot,
+                manifest.created_at,
+                manifest.sync_progress,
+                manifest.prune_progress,
+                manifest.epochs
+            );
+            return Ok(());
+        }
+        Some(("export-metadata", sub_matches)) => {
+            let (db_path, storage_config, file) = export_metadata_args(&c, sub_matches)?;
+            tracing_subscriber::fmt().init();
+            let export = Storage::open_read_only(&db_path, &storage_config)?
+                .export_metadata()
+                .await?;
+            export.write(&file)?;
+            info!(
+                "metadata of {:?} blobs exported to {:?} at block {:?}",
+                export.blobs.len(),
+                file,
+                export.block_number
+            );
+            return Ok(());
+        }
+        Some(("db", sub_matches)) => {
+            let (db_path, storage_config, command) = db_args(&c, sub_matches)?;
+            tracing_subscriber::fmt().init();
+            let db = Storage::open_read_only(&db_path, &storage_config)?;
+            run_db_command(&db, command).await?;
+            return Ok(());
+        }
+        _ => {}
+    }
+    let config = Config::from_raw(&c)?;
 
     // tracing
 
@@ -0,0 +1,72 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use storage::Storage;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    time::sleep,
+};
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+
+#[derive(Debug, Clone)]
+pub struct SnapshotConfig {
+    // directory of the snapshots, named `snapshot-<unix timestamp>`
+    pub dir: PathBuf,
+    // snapshots are only taken on SIGUSR1 if unset
+    pub interval: Option<Duration>,
+    // newest snapshots kept, older ones are deleted
+    pub keep: usize,
+}
+
+/// Takes snapshots of the database on schedule and on SIGUSR1.
+pub fn start_snapshots(db: Arc<Storage>, config: SnapshotConfig) -> Result<()> {
+    let mut trigger = signal(SignalKind::user_defined1())?;
+    tokio::spawn(async move {
+        loop {
+            match config.interval {
+                Some(interval) => {
+                    tokio::select! {
+                        _ = sleep(interval) => {}
+                        _ = trigger.recv() => {}
+                    }
+                }
+                None => {
+                    trigger.recv().await;
+                }
+            }
+            if let Err(e) = take_snapshot(&db, &config).await {
+                error!("failed to take database snapshot, e={:?}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+async fn take_snapshot(db: &Storage, config: &SnapshotConfig) -> Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    db.snapshot(config.dir.join(format!("{}{}", SNAPSHOT_PREFIX, timestamp)))
+        .await?;
+    remove_old_snapshots(&config.dir, config.keep)
+}
+
+fn remove_old_snapshots(dir: &Path, keep: usize) -> Result<()> {
+    let mut snapshots = vec![];
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(Ok(timestamp)) = name.strip_prefix(SNAPSHOT_PREFIX).map(str::parse::<u64>) {
+            snapshots.push((timestamp, name));
+        }
+    }
+    snapshots.sort();
+    for (_, name) in snapshots.iter().rev().skip(keep) {
+        info!("removing database snapshot {:?}", name);
+        fs::remove_dir_all(dir.join(name))?;
+    }
+    Ok(())
+}
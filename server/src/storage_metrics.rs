@@ -0,0 +1,166 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use storage::Storage;
+use tokio::time::sleep;
+use utils::metrics;
+
+/// Exports RocksDB statistics and stored slice counts every `interval`.
+pub fn start_storage_metrics(db: Arc<Storage>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut exported = ExportedLabels::default();
+        loop {
+            if let Err(e) = export_storage_metrics(&db, &mut exported).await {
+                error!("failed to export storage metrics, e={:?}", e);
+            }
+            sleep(interval).await;
+        }
+    });
+}
+
+// label sets set by the last export, removed once their database, column or epoch is gone
+#[derive(Default)]
+struct ExportedLabels {
+    databases: HashSet<String>,
+    columns: HashSet<(String, &'static str)>,
+    epochs: HashSet<String>,
+}
+
+async fn export_storage_metrics(db: &Storage, exported: &mut ExportedLabels) -> Result<()> {
+    // collected first, so that scrapes never see the gauges half updated
+    let database_stats = db.database_stats()?;
+    let epoch_stats = db.epoch_stats().await?;
+
+    let mut labels = ExportedLabels::default();
+    for stats in database_stats {
+        let database = stats.database.as_str();
+        metrics::DB_SIZE_BYTES
+            .with_label_values(&[database])
+            .set(stats.size_bytes as i64);
+        for (column, keys) in stats.column_keys {
+            metrics::DB_COLUMN_KEYS
+                .with_label_values(&[database, column])
+                .set(keys as i64);
+            labels.columns.insert((database.to_string(), column));
+        }
+        for (column, bytes) in stats.column_bytes {
+            metrics::DB_COLUMN_SIZE_BYTES
+                .with_label_values(&[database, column])
+                .set(bytes as i64);
+            labels.columns.insert((database.to_string(), column));
+        }
+        let lookups = stats.block_cache_hits + stats.block_cache_misses;
+        if lookups > 0 {
+            metrics::DB_BLOCK_CACHE_HIT_RATIO
+                .with_label_values(&[database])
+                .set(stats.block_cache_hits as f64 / lookups as f64);
+        } else {
+            let _ = metrics::DB_BLOCK_CACHE_HIT_RATIO.remove_label_values(&[database]);
+        }
+        metrics::DB_WRITE_STALL_SECONDS
+            .with_label_values(&[database])
+            .set(stats.write_stall_micros as f64 / 1e6);
+        metrics::DB_PENDING_COMPACTION_BYTES
+            .with_label_values(&[database])
+            .set(stats.pending_compaction_bytes as i64);
+        labels.databases.insert(stats.database);
+    }
+
+    let mut stored_slices = 0;
+    for stats in epoch_stats {
+        let epoch = stats.epoch.to_string();
+        metrics::EPOCH_BLOBS
+            .with_label_values(&[&epoch])
+            .set(stats.blobs as i64);
+        metrics::EPOCH_SLICES
+            .with_label_values(&[&epoch])
+            .set(stats.slices as i64);
+        metrics::EPOCH_BYTES
+            .with_label_values(&[&epoch])
+            .set(stats.bytes as i64);
+        stored_slices += stats.slices;
+        labels.epochs.insert(epoch);
+    }
+    metrics::STORED_SLICES.set(stored_slices as i64);
+
+    // pruned partitions and epochs disappear from the labels
+    for database in exported.databases.difference(&labels.databases) {
+        let _ = metrics::DB_SIZE_BYTES.remove_label_values(&[database]);
+        let _ = metrics::DB_BLOCK_CACHE_HIT_RATIO.remove_label_values(&[database]);
+        let _ = metrics::DB_WRITE_STALL_SECONDS.remove_label_values(&[database]);
+        let _ = metrics::DB_PENDING_COMPACTION_BYTES.remove_label_values(&[database]);
+    }
+    for (database, column) in exported.columns.difference(&labels.columns) {
+        let _ = metrics::DB_COLUMN_KEYS.remove_label_values(&[database, column]);
+        let _ = metrics::DB_COLUMN_SIZE_BYTES.remove_label_values(&[database, column]);
+    }
+    for epoch in exported.epochs.difference(&labels.epochs) {
+        let _ = metrics::EPOCH_BLOBS.remove_label_values(&[epoch]);
+        let _ = metrics::EPOCH_SLICES.remove_label_values(&[epoch]);
+        let _ = metrics::EPOCH_BYTES.remove_label_values(&[epoch]);
+    }
+    *exported = labels;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+    use storage::{slice_db::SliceDB, StorageConfig};
+
+    #[tokio::test]
+    async fn export_storage_metrics_test() {
+        let path = env::temp_dir().join(format!("zg-da-node-storage-metrics-{}", process::id()));
+        let db = Storage::new(&path, &StorageConfig::default()).unwrap();
+        db.put_slice(2, 0, [1; 32], vec![]).await.unwrap();
+        db.put_slice(3, 0, [1; 32], vec![]).await.unwrap();
+        let mut exported = ExportedLabels::default();
+        export_storage_metrics(&db, &mut exported).await.unwrap();
+
+        assert!(metrics::DB_SIZE_BYTES.with_label_values(&["main"]).get() > 0);
+        assert_eq!(
+            metrics::DB_COLUMN_KEYS
+                .with_label_values(&["2-2", "blob_index"])
+                .get(),
+            1
+        );
+        assert!(
+            metrics::DB_COLUMN_SIZE_BYTES
+                .with_label_values(&["2-2", "blob_index"])
+                .get()
+                > 0
+        );
+        assert_eq!(
+            metrics::DB_PENDING_COMPACTION_BYTES
+                .with_label_values(&["2-2"])
+                .get(),
+            0
+        );
+        assert_eq!(metrics::EPOCH_BLOBS.with_label_values(&["2"]).get(), 1);
+        assert_eq!(metrics::EPOCH_SLICES.with_label_values(&["2"]).get(), 0);
+        // the partition holds this epoch only
+        assert_eq!(
+            metrics::EPOCH_BYTES.with_label_values(&["2"]).get(),
+            metrics::DB_COLUMN_SIZE_BYTES
+                .with_label_values(&["2-2", "blob_index"])
+                .get()
+        );
+        assert_eq!(metrics::STORED_SLICES.get(), 0);
+
+        // pruned partitions and epochs are removed, the others are kept
+        db.prune(2).await.unwrap();
+        export_storage_metrics(&db, &mut exported).await.unwrap();
+        assert!(metrics::EPOCH_BLOBS.remove_label_values(&["2"]).is_err());
+        assert!(metrics::DB_SIZE_BYTES
+            .remove_label_values(&["2-2"])
+            .is_err());
+        assert!(metrics::DB_COLUMN_KEYS
+            .remove_label_values(&["2-2", "blob_index"])
+            .is_err());
+        assert_eq!(metrics::EPOCH_BLOBS.with_label_values(&["3"]).get(), 1);
+        assert!(metrics::DB_SIZE_BYTES.with_label_values(&["3-3"]).get() > 0);
+        drop(db);
+        fs::remove_dir_all(path).unwrap();
+    }
+}
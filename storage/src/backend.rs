@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, Weak},
+};
+
+use anyhow::{bail, Result};
+use kvdb::{DBKeyValue, KeyValueDB};
+use kvdb_memorydb::InMemory;
+
+use crate::{
+    database::{ColumnConfig, Database},
+    partition::{
+        PARTITION_COL_BLOB_INDEX, PARTITION_COL_NUM, PARTITION_COL_SLICE, PARTITION_COL_SLICE_DATA,
+    },
+    stats::{COLUMN_NAMES, PARTITION_COLUMN_NAMES},
+    DatabaseStats, StorageConfig, COL_NUM,
+};
+
+// default memory budgets in MiB, split by RocksDB between the write buffer and the block cache:
+// the blob index is small and scanned by mining, row data is large and read in bulk
+const DEFAULT_BLOB_INDEX_MEMORY_BUDGET: usize = 64;
+const DEFAULT_SLICE_MEMORY_BUDGET: usize = 256;
+const DEFAULT_SLICE_DATA_MEMORY_BUDGET: usize = 256;
+
+// directory of the slice partitions under the data path
+const SLICE_PARTITIONS_DIR: &str = "slices";
+// directory of the main database's logs under the secondary directory, next to the partitions'
+const MAIN_SECONDARY_DIR: &str = "main";
+// name of the main database in statistics
+pub(crate) const MAIN_DATABASE: &str = "main";
+
+/// The key-value databases behind `Storage`: a main database with `COL_NUM` columns, and named
+/// slice partitions with their own columns.
+pub trait StorageBackend: Send + Sync {
+    fn main(&self) -> Arc<dyn KeyValueDB>;
+
+    /// Iterates a column of the main database in key order from `start` on, which
+    /// `KeyValueDB` cannot seek to.
+    fn iter_main_from<'a>(
+        &'a self,
+        col: u32,
+        start: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a>;
+
+    /// Names of the existing partitions.
+    fn partitions(&self) -> Result<Vec<String>>;
+
+    /// Opens a partition, creating it if missing.
+    fn open_partition(&self, name: &str) -> Result<Arc<dyn KeyValueDB>>;
+
+    /// Deletes a partition and its data.
+    fn remove_partition(&self, name: &str) -> Result<()>;
+
+    /// Creates checkpoints of the main database and of the given open partitions in `dir`, laid
+    /// out as a data path.
+    fn checkpoint(&self, _dir: &Path, _partitions: &[String]) -> Result<()> {
+        bail!("checkpoints are not supported by this backend")
+    }
+
+    /// Statistics of the main database and the open partitions, if the backend keeps any.
+    fn database_stats(&self) -> Result<Vec<DatabaseStats>> {
+        Ok(vec![])
+    }
+}
+
+/// RocksDB databases in the data directory, with the partitions under `slices`.
+pub struct RocksDbBackend {
+    db: Arc<Database>,
+    path: PathBuf,
+    partitions_dir: PathBuf,
+    // partitions opened and not dropped yet, for statistics
+    opened: Mutex<HashMap<String, Weak<Database>>>,
+    partition_columns: Vec<ColumnConfig>,
+    // logs of the secondary instances if opened read-only
+    secondary_dir: Option<PathBuf>,
+}
+
+impl RocksDbBackend {
+    pub fn open(path: impl AsRef<Path>, config: &StorageConfig) -> Result<Self> {
+        Self::open_with(path.as_ref(), config, None)
+    }
+
+    /// Opens the databases as RocksDB secondary instances, which read alongside a running node
+    /// and refuse writes.
+    pub fn open_read_only(path: impl AsRef<Path>, config: &StorageConfig) -> Result<Self> {
+        let secondary_dir = env::temp_dir().join(format!("zg-da-node-secondary-{}", process::id()));
+        Self::open_with(path.as_ref(), config, Some(secondary_dir))
+    }
+
+    fn open_with(
+        path: &Path,
+        config: &StorageConfig,
+        secondary_dir: Option<PathBuf>,
+    ) -> Result<Self> {
+        let columns = vec![ColumnConfig::default(); COL_NUM as usize];
+        let db = Arc::new(match &secondary_dir {
+            Some(dir) => Database::open_secondary(path, &dir.join(MAIN_SECONDARY_DIR), &columns)?,
+            None => Database::open(path, &columns)?,
+        });
+
+        let mut partition_columns = vec![ColumnConfig::default(); PARTITION_COL_NUM as usize];
+        for (col, column_config, memory_budget) in [
+            (
+                PARTITION_COL_BLOB_INDEX,
+                &config.blob_index,
+                DEFAULT_BLOB_INDEX_MEMORY_BUDGET,
+            ),
+            (
+                PARTITION_COL_SLICE,
+                &config.slice,
+                DEFAULT_SLICE_MEMORY_BUDGET,
+            ),
+            (
+                PARTITION_COL_SLICE_DATA,
+                &config.slice_data,
+                DEFAULT_SLICE_DATA_MEMORY_BUDGET,
+            ),
+        ] {
+            partition_columns[col as usize] = ColumnConfig {
+                memory_budget: column_config.memory_budget.or(Some(memory_budget)),
+                ..column_config.clone()
+            };
+        }
+        let partitions_dir = path.join(SLICE_PARTITIONS_DIR);
+        if secondary_dir.is_none() {
+            fs::create_dir_all(&partitions_dir)?;
+        }
+        Ok(Self {
+            db,
+            path: path.to_path_buf(),
+            partitions_dir,
+            opened: Default::default(),
+            partition_columns,
+            secondary_dir,
+        })
+    }
+}
+
+impl Drop for RocksDbBackend {
+    fn drop(&mut self) {
+        if let Some(dir) = &self.secondary_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+impl StorageBackend for RocksDbBackend {
+    fn main(&self) -> Arc<dyn KeyValueDB> {
+        self.db.clone()
+    }
+
+    fn iter_main_from<'a>(
+        &'a self,
+        col: u32,
+        start: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        self.db.iter_from_key(col, start)
+    }
+
+    fn partitions(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        if !self.partitions_dir.exists() {
+            return Ok(names);
+        }
+        for entry in fs::read_dir(&self.partitions_dir)? {
+            names.push(entry?.file_name().to_string_lossy().to_string());
+        }
+        Ok(names)
+    }
+
+    fn open_partition(&self, name: &str) -> Result<Arc<dyn KeyValueDB>> {
+        let path = self.partitions_dir.join(name);
+        let db = Arc::new(match &self.secondary_dir {
+            Some(dir) => Database::open_secondary(&path, &dir.join(name), &self.partition_columns)?,
+            None => Database::open(&path, &self.partition_columns)?,
+        });
+        self.opened
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::downgrade(&db));
+        Ok(db)
+    }
+
+    fn remove_partition(&self, name: &str) -> Result<()> {
+        if self.secondary_dir.is_some() {
+            bail!("cannot remove partition {:?} of a read-only database", name);
+        }
+        self.opened.lock().unwrap().remove(name);
+        fs::remove_dir_all(self.partitions_dir.join(name))?;
+        Ok(())
+    }
+
+    fn checkpoint(&self, dir: &Path, partitions: &[String]) -> Result<()> {
+        self.db.checkpoint(dir)?;
+        let partitions_dir = dir.join(SLICE_PARTITIONS_DIR);
+        fs::create_dir_all(&partitions_dir)?;
+        for name in partitions {
+            let db = self
+                .opened
+                .lock()
+                .unwrap()
+                .get(name)
+                .and_then(Weak::upgrade);
+            match db {
+                Some(db) => db.checkpoint(&partitions_dir.join(name))?,
+                None => bail!("partition {:?} is not open", name),
+            }
+        }
+        Ok(())
+    }
+
+    fn database_stats(&self) -> Result<Vec<DatabaseStats>> {
+        let mut stats = vec![rocksdb_stats(
+            MAIN_DATABASE,
+            &self.db,
+            &self.path,
+            &COLUMN_NAMES,
+        )?];
+        let opened: Vec<_> = self
+            .opened
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(name, db)| Some((name.clone(), db.upgrade()?)))
+            .collect();
+        for (name, db) in opened {
+            // skips partitions removed by pruning meanwhile
+            if let Ok(partition_stats) = rocksdb_stats(
+                &name,
+                &db,
+                &self.partitions_dir.join(&name),
+                &PARTITION_COLUMN_NAMES,
+            ) {
+                stats.push(partition_stats);
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+fn rocksdb_stats(
+    name: &str,
+    db: &Database,
+    path: &Path,
+    column_names: &[&'static str],
+) -> Result<DatabaseStats> {
+    let (mut column_keys, mut column_bytes, mut pending_compaction_bytes) = (vec![], vec![], 0);
+    for (col, &column) in column_names.iter().enumerate() {
+        column_keys.push((column, db.num_keys(col as u32)?));
+        column_bytes.push((column, db.sst_bytes(col as u32)?));
+        pending_compaction_bytes += db.pending_compaction_bytes(col as u32)?;
+    }
+    let tickers = db.tickers();
+    let ticker = |name: &str| tickers.get(name).copied().unwrap_or(0);
+    Ok(DatabaseStats {
+        database: name.to_string(),
+        size_bytes: dir_size(path)?,
+        column_keys,
+        column_bytes,
+        block_cache_hits: ticker("block.cache.hit"),
+        block_cache_misses: ticker("block.cache.miss"),
+        write_stall_micros: ticker("stall.micros"),
+        pending_compaction_bytes,
+    })
+}
+
+/// Databases in memory, dropped with the backend, for tests.
+pub struct MemoryBackend {
+    db: Arc<InMemory>,
+    partitions: Mutex<HashMap<String, Arc<InMemory>>>,
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self {
+            db: Arc::new(kvdb_memorydb::create(COL_NUM)),
+            partitions: Default::default(),
+        }
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn main(&self) -> Arc<dyn KeyValueDB> {
+        self.db.clone()
+    }
+
+    fn iter_main_from<'a>(
+        &'a self,
+        col: u32,
+        start: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        // the in-memory columns are iterated in key order
+        Box::new(
+            self.db
+                .iter(col)
+                .skip_while(move |item| item.as_ref().is_ok_and(|(key, _)| key.as_slice() < start)),
+        )
+    }
+
+    fn partitions(&self) -> Result<Vec<String>> {
+        Ok(self.partitions.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn open_partition(&self, name: &str) -> Result<Arc<dyn KeyValueDB>> {
+        Ok(self
+            .partitions
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(kvdb_memorydb::create(PARTITION_COL_NUM)))
+            .clone())
+    }
+
+    fn remove_partition(&self, name: &str) -> Result<()> {
+        self.partitions.lock().unwrap().remove(name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_backend_test() {
+        let backend = MemoryBackend::default();
+        assert!(backend.partitions().unwrap().is_empty());
+        let partition = backend.open_partition("1-1").unwrap();
+        let mut tx = partition.transaction();
+        tx.put(PARTITION_COL_SLICE_DATA, &[1], &[2]);
+        partition.write(tx).unwrap();
+        backend.open_partition("2-3").unwrap();
+
+        // partitions opened again keep their data
+        let reopened = backend.open_partition("1-1").unwrap();
+        assert_eq!(
+            reopened.get(PARTITION_COL_SLICE_DATA, &[1]).unwrap(),
+            Some(vec![2])
+        );
+        assert!(backend
+            .main()
+            .get(PARTITION_COL_SLICE_DATA, &[1])
+            .unwrap()
+            .is_none());
+        let mut names = backend.partitions().unwrap();
+        names.sort();
+        assert_eq!(names, vec!["1-1", "2-3"]);
+
+        backend.remove_partition("1-1").unwrap();
+        assert_eq!(backend.partitions().unwrap(), vec!["2-3"]);
+        // removed partitions are created empty again
+        let recreated = backend.open_partition("1-1").unwrap();
+        assert!(recreated
+            .get(PARTITION_COL_SLICE_DATA, &[1])
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,11 +1,13 @@
-use crate::COL_BLOB_STATUS;
+use crate::{COL_BLOB_STATUS, COL_ERASURE_COMMITMENT};
 
 use super::Storage;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use std::convert::TryFrom;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BlobStatus {
     UPLOADED = 1,
     VERIFIED = 2,
@@ -38,9 +40,28 @@ pub trait BlobStatusDB {
         quorum_id: u64,
         storage_root: [u8; 32],
     ) -> Result<Option<BlobStatus>>;
+    /// The epoch, quorum and status of each blob with the root, by a full scan.
+    async fn find_blob_statuses(
+        &self,
+        storage_root: [u8; 32],
+    ) -> Result<Vec<(u64, u64, BlobStatus)>>;
+    async fn put_erasure_commitment(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        erasure_commitment: Vec<u8>,
+    ) -> Result<()>;
+    async fn get_erasure_commitment(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+    ) -> Result<Option<Vec<u8>>>;
+    async fn prune_erasure_commitments(&self, epoch: u64) -> Result<()>;
 }
 
-fn get_blob_key(epoch: u64, quorum_id: u64, storage_root: [u8; 32]) -> Vec<u8> {
+pub(crate) fn get_blob_key(epoch: u64, quorum_id: u64, storage_root: [u8; 32]) -> Vec<u8> {
     epoch
         .to_be_bytes()
         .into_iter()
@@ -80,4 +101,94 @@ impl BlobStatusDB for Storage {
         }
         Ok(None)
     }
+
+    async fn find_blob_statuses(
+        &self,
+        storage_root: [u8; 32],
+    ) -> Result<Vec<(u64, u64, BlobStatus)>> {
+        let mut statuses = vec![];
+        for item in self.db.iter(COL_BLOB_STATUS) {
+            let (key, raw_data) = item?;
+            if key.len() != 48 || key[16..] != storage_root {
+                continue;
+            }
+            let status: BlobStatus = u64::from_be_bytes(raw_data.try_into().unwrap())
+                .try_into()
+                .map_err(|_| anyhow!("error when convert u64 to BlobStatus"))?;
+            statuses.push((
+                u64::from_be_bytes(key[..8].try_into().unwrap()),
+                u64::from_be_bytes(key[8..16].try_into().unwrap()),
+                status,
+            ));
+        }
+        Ok(statuses)
+    }
+
+    async fn put_erasure_commitment(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        erasure_commitment: Vec<u8>,
+    ) -> Result<()> {
+        let key = get_blob_key(epoch, quorum_id, storage_root);
+        let mut tx = self.db.transaction();
+        tx.put(COL_ERASURE_COMMITMENT, &key, &erasure_commitment);
+        self.db.write(tx)?;
+        Ok(())
+    }
+
+    async fn get_erasure_commitment(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+    ) -> Result<Option<Vec<u8>>> {
+        let key = get_blob_key(epoch, quorum_id, storage_root);
+        Ok(self.db.get(COL_ERASURE_COMMITMENT, &key)?)
+    }
+
+    async fn prune_erasure_commitments(&self, epoch: u64) -> Result<()> {
+        let mut tx = self.db.transaction();
+        tx.delete_prefix(COL_ERASURE_COMMITMENT, &epoch.to_be_bytes());
+        self.db.write(tx)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn erasure_commitment_test() {
+        let storage = Storage::in_memory();
+        storage
+            .put_erasure_commitment(1, 0, [1; 32], vec![1])
+            .await
+            .unwrap();
+        storage
+            .put_erasure_commitment(2, 0, [1; 32], vec![2])
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_erasure_commitment(1, 0, [1; 32]).await.unwrap(),
+            Some(vec![1])
+        );
+        assert_eq!(
+            storage.get_erasure_commitment(1, 1, [1; 32]).await.unwrap(),
+            None
+        );
+
+        // only the pruned epoch is removed
+        storage.prune_erasure_commitments(1).await.unwrap();
+        assert_eq!(
+            storage.get_erasure_commitment(1, 0, [1; 32]).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            storage.get_erasure_commitment(2, 0, [1; 32]).await.unwrap(),
+            Some(vec![2])
+        );
+    }
 }
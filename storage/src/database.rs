@@ -0,0 +1,389 @@
+use std::{collections::HashMap, io, iter, path::Path, str::FromStr, thread};
+
+use anyhow::{bail, Error};
+use kvdb::{DBKeyValue, DBOp, DBTransaction, DBValue, KeyValueDB};
+use rocksdb::{
+    checkpoint::Checkpoint, BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor,
+    DBCompressionType, Direction, IteratorMode, Options, ReadOptions, WriteBatch, DB,
+};
+
+const MB: usize = 1024 * 1024;
+
+// defaults of the columns without settings, as kvdb-rocksdb used to have them
+const DEFAULT_MEMORY_BUDGET: usize = 128;
+const DEFAULT_BLOOM_FILTER_BITS: u32 = 10;
+const TARGET_FILE_SIZE: u64 = 64 * MB as u64;
+const BLOCK_SIZE: usize = 16 * 1024;
+
+/// Compression of the blocks of a column. RocksDB is built with Snappy only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    None,
+    #[default]
+    Snappy,
+}
+
+impl FromStr for Compression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "snappy" => Ok(Self::Snappy),
+            _ => bail!("unknown compression {:?}, expected none or snappy", s),
+        }
+    }
+}
+
+/// RocksDB settings of a column, defaulted if unset.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnConfig {
+    // MiB split between the write buffers and the block cache if it is unset
+    pub memory_budget: Option<usize>,
+    // MiB of the block cache of the column, 0 to disable it; a third of the memory budget if unset
+    pub block_cache: Option<usize>,
+    pub compression: Option<Compression>,
+    // bits per key of the bloom filters, 0 to disable them
+    pub bloom_filter_bits: Option<u32>,
+}
+
+impl ColumnConfig {
+    fn options(&self) -> Options {
+        let memory_budget = self.memory_budget.unwrap_or(DEFAULT_MEMORY_BUDGET);
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_size(BLOCK_SIZE);
+        block_opts.set_format_version(5);
+        block_opts.set_block_restart_interval(16);
+        match self.block_cache.unwrap_or(memory_budget / 3) {
+            0 => block_opts.disable_cache(),
+            block_cache => {
+                block_opts.set_block_cache(&Cache::new_lru_cache(block_cache * MB));
+                block_opts.set_cache_index_and_filter_blocks(true);
+                block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+            }
+        }
+        match self.bloom_filter_bits.unwrap_or(DEFAULT_BLOOM_FILTER_BITS) {
+            0 => {}
+            bits => block_opts.set_bloom_filter(bits as f64, true),
+        }
+
+        let mut opts = Options::default();
+        opts.set_level_compaction_dynamic_level_bytes(true);
+        opts.set_block_based_table_factory(&block_opts);
+        opts.optimize_level_style_compaction(memory_budget * MB);
+        opts.set_target_file_size_base(TARGET_FILE_SIZE);
+        // the same compression at every level, instead of the one set by the optimization
+        opts.set_compression_per_level(&[]);
+        opts.set_compression_type(match self.compression.unwrap_or_default() {
+            Compression::None => DBCompressionType::None,
+            Compression::Snappy => DBCompressionType::Snappy,
+        });
+        opts
+    }
+}
+
+fn other_io_err(e: impl ToString) -> io::Error {
+    io::Error::other(e.to_string())
+}
+
+fn db_options(secondary: bool) -> Options {
+    let mut opts = Options::default();
+    opts.set_report_bg_io_stats(true);
+    opts.enable_statistics();
+    opts.set_use_fsync(false);
+    opts.create_if_missing(!secondary);
+    // secondary instances keep all files open
+    opts.set_max_open_files(if secondary { -1 } else { 512 });
+    opts.set_bytes_per_sync(MB as u64);
+    opts.set_keep_log_file_num(1);
+    let cpus = thread::available_parallelism().map_or(1, |x| x.get());
+    opts.increase_parallelism((cpus as i32 / 2).max(1));
+    opts
+}
+
+fn read_options() -> ReadOptions {
+    let mut read_opts = ReadOptions::default();
+    read_opts.set_verify_checksums(false);
+    read_opts
+}
+
+/// A RocksDB database with the columns `col0`, `col1`, ..., each with its own settings.
+pub struct Database {
+    db: DB,
+    column_names: Vec<String>,
+    opts: Options,
+}
+
+impl Database {
+    /// Opens the database in `path`, creating it and any column it misses.
+    pub fn open(path: &Path, columns: &[ColumnConfig]) -> io::Result<Self> {
+        let opts = db_options(false);
+        let column_names = column_names(columns);
+        // missing databases have no columns yet
+        let existing = DB::list_cf(&opts, path).unwrap_or_default();
+        let descriptors = column_names
+            .iter()
+            .zip(columns)
+            .filter(|(name, _)| existing.contains(name))
+            .map(|(name, config)| ColumnFamilyDescriptor::new(name, config.options()));
+        let mut db = DB::open_cf_descriptors(&opts, path, descriptors).map_err(other_io_err)?;
+        for (name, config) in column_names.iter().zip(columns) {
+            if !existing.contains(name) {
+                db.create_cf(name, &config.options())
+                    .map_err(other_io_err)?;
+            }
+        }
+        Ok(Self {
+            db,
+            column_names,
+            opts,
+        })
+    }
+
+    /// Opens the database in `path` as a secondary instance, which reads alongside the primary
+    /// and keeps its logs in `secondary_path`.
+    pub fn open_secondary(
+        path: &Path,
+        secondary_path: &Path,
+        columns: &[ColumnConfig],
+    ) -> io::Result<Self> {
+        let opts = db_options(true);
+        let column_names = column_names(columns);
+        let descriptors = column_names
+            .iter()
+            .zip(columns)
+            .map(|(name, config)| ColumnFamilyDescriptor::new(name, config.options()));
+        let db = DB::open_cf_descriptors_as_secondary(&opts, path, secondary_path, descriptors)
+            .map_err(other_io_err)?;
+        Ok(Self {
+            db,
+            column_names,
+            opts,
+        })
+    }
+
+    fn cf(&self, col: u32) -> io::Result<&ColumnFamily> {
+        self.column_names
+            .get(col as usize)
+            .and_then(|name| self.db.cf_handle(name))
+            .ok_or_else(|| other_io_err(format!("no such column family: {}", col)))
+    }
+
+    fn iter_from<'a>(
+        &'a self,
+        col: u32,
+        read_opts: ReadOptions,
+        mode: IteratorMode,
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        match self.cf(col) {
+            Ok(cf) => Box::new(self.db.iterator_cf_opt(cf, read_opts, mode).map(|item| {
+                item.map(|(key, value)| (key.into_vec().into(), value.into()))
+                    .map_err(other_io_err)
+            })),
+            Err(e) => Box::new(iter::once(Err(e))),
+        }
+    }
+
+    /// Iterates a column in key order from `start` on.
+    pub fn iter_from_key<'a>(
+        &'a self,
+        col: u32,
+        start: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        self.iter_from(
+            col,
+            read_options(),
+            IteratorMode::From(start, Direction::Forward),
+        )
+    }
+
+    fn int_property(&self, col: u32, name: &str) -> io::Result<u64> {
+        Ok(self
+            .db
+            .property_int_value_cf(self.cf(col)?, name)
+            .map_err(other_io_err)?
+            .unwrap_or_default())
+    }
+
+    /// The estimated number of keys in a column.
+    pub fn num_keys(&self, col: u32) -> io::Result<u64> {
+        self.int_property(col, "rocksdb.estimate-num-keys")
+    }
+
+    /// The size of the SST files of a column, including files of older versions still read.
+    pub fn sst_bytes(&self, col: u32) -> io::Result<u64> {
+        self.int_property(col, "rocksdb.total-sst-files-size")
+    }
+
+    /// The estimated bytes compaction has to rewrite to bring a column's levels under their
+    /// target sizes.
+    pub fn pending_compaction_bytes(&self, col: u32) -> io::Result<u64> {
+        self.int_property(col, "rocksdb.estimate-pending-compaction-bytes")
+    }
+
+    /// Creates a checkpoint of the database in `path`, which must not exist: an openable copy
+    /// whose files are hard links to the immutable files of the database where possible.
+    pub fn checkpoint(&self, path: &Path) -> io::Result<()> {
+        Checkpoint::new(&self.db)
+            .and_then(|checkpoint| checkpoint.create_checkpoint(path))
+            .map_err(other_io_err)
+    }
+
+    /// RocksDB tickers by name without the `rocksdb.` prefix, counted since the database was
+    /// opened.
+    pub fn tickers(&self) -> HashMap<String, u64> {
+        let statistics = self.opts.get_statistics().unwrap_or_default();
+        statistics
+            .lines()
+            .filter_map(|line| {
+                // e.g. "rocksdb.block.cache.hit COUNT : 5", histograms have percentiles first
+                let (name, value) = line.split_once(' ')?;
+                Some((
+                    name.strip_prefix("rocksdb.")?.to_string(),
+                    value.strip_prefix("COUNT : ")?.parse().ok()?,
+                ))
+            })
+            .collect()
+    }
+}
+
+fn column_names(columns: &[ColumnConfig]) -> Vec<String> {
+    (0..columns.len())
+        .map(|col| format!("col{}", col))
+        .collect()
+}
+
+impl KeyValueDB for Database {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        Ok(self
+            .db
+            .get_pinned_cf_opt(self.cf(col)?, key, &read_options())
+            .map_err(other_io_err)?
+            .map(|value| value.to_vec()))
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> io::Result<Option<DBValue>> {
+        self.iter_with_prefix(col, prefix)
+            .next()
+            .transpose()
+            .map(|item| item.map(|(_, value)| value))
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        let mut batch = WriteBatch::default();
+        for op in transaction.ops {
+            let cf = self.cf(op.col())?;
+            match op {
+                DBOp::Insert { key, value, .. } => batch.put_cf(cf, &key, &value),
+                DBOp::Delete { key, .. } => batch.delete_cf(cf, &key),
+                DBOp::DeletePrefix { col, prefix } => match kvdb::end_prefix(&prefix) {
+                    Some(end) => batch.delete_range_cf(cf, &prefix[..], &end[..]),
+                    // prefixes of 0xff bytes only have no end, their keys are deleted one by one
+                    None => {
+                        for item in self.iter_with_prefix(col, &prefix) {
+                            batch.delete_cf(cf, &item?.0);
+                        }
+                    }
+                },
+            }
+        }
+        self.db.write(batch).map_err(other_io_err)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        self.iter_from(col, read_options(), IteratorMode::Start)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        let mut read_opts = read_options();
+        match kvdb::end_prefix(prefix) {
+            Some(end) => read_opts.set_iterate_upper_bound(end),
+            // RocksDB takes no empty upper bound
+            None => {
+                return Box::new(
+                    self.iter_from(
+                        col,
+                        read_opts,
+                        IteratorMode::From(prefix, Direction::Forward),
+                    )
+                    .take_while(move |item| {
+                        item.as_ref()
+                            .map_or(true, |(key, _)| key.starts_with(prefix))
+                    }),
+                )
+            }
+        }
+        self.iter_from(
+            col,
+            read_opts,
+            IteratorMode::From(prefix, Direction::Forward),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn database_test() {
+        let path = env::temp_dir().join(format!("zg-da-node-database-{}", process::id()));
+        let columns = vec![
+            ColumnConfig {
+                compression: Some(Compression::None),
+                bloom_filter_bits: Some(0),
+                block_cache: Some(0),
+                ..Default::default()
+            },
+            ColumnConfig::default(),
+        ];
+        let db = Database::open(&path, &columns[..1]).unwrap();
+        let mut tx = db.transaction();
+        tx.put(0, &[1, 1], &[1]);
+        tx.put(0, &[1, 2], &[2]);
+        tx.put(0, &[2], &[3]);
+        tx.put(0, &[0xff, 1], &[4]);
+        db.write(tx).unwrap();
+        assert!(db.get(1, &[1]).is_err());
+        drop(db);
+
+        // columns added since the database was created are created on open
+        let db = Database::open(&path, &columns).unwrap();
+        assert_eq!(db.get(0, &[2]).unwrap(), Some(vec![3]));
+        let prefixed: Vec<_> = db
+            .iter_with_prefix(0, &[1])
+            .map(|item| item.unwrap().1)
+            .collect();
+        assert_eq!(prefixed, vec![vec![1], vec![2]]);
+        let from: Vec<_> = db
+            .iter_from_key(0, &[1, 2])
+            .map(|item| item.unwrap().1)
+            .collect();
+        assert_eq!(from, vec![vec![2], vec![3], vec![4]]);
+        let mut tx = db.transaction();
+        tx.delete_prefix(0, &[1]);
+        tx.delete_prefix(0, &[0xff]);
+        tx.put(1, &[1], &[5]);
+        db.write(tx).unwrap();
+        let keys: Vec<_> = db.iter(0).map(|item| item.unwrap().0.to_vec()).collect();
+        assert_eq!(keys, vec![vec![2]]);
+        assert_eq!(db.get_by_prefix(1, &[]).unwrap(), Some(vec![5]));
+        drop(db);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn compression_test() {
+        assert_eq!("none".parse::<Compression>().unwrap(), Compression::None);
+        assert_eq!(
+            "snappy".parse::<Compression>().unwrap(),
+            Compression::Snappy
+        );
+        assert!("zstd".parse::<Compression>().is_err());
+    }
+}
@@ -1,29 +1,118 @@
 use std::{path::Path, sync::Arc};
 
 use anyhow::Result;
-use kvdb_rocksdb::{Database, DatabaseConfig};
+use kvdb::KeyValueDB;
+use partition::SlicePartitions;
 
+mod backend;
 pub mod blob_status_db;
+mod database;
+mod metadata;
 pub mod misc_db;
+mod partition;
 pub mod quorum_db;
+pub mod registration_db;
+mod schema;
 pub mod slice_db;
+mod snapshot;
+mod stats;
+pub mod transaction_db;
 
-pub const COL_NUM: u32 = 5;
+pub use backend::{MemoryBackend, RocksDbBackend, StorageBackend};
+pub use database::{ColumnConfig, Compression};
+pub use metadata::{ExportedBlob, MetadataExport, METADATA_FORMAT_VERSION};
+pub use schema::SCHEMA_VERSION;
+pub use snapshot::{SnapshotManifest, SNAPSHOT_MANIFEST};
+pub use stats::{ColumnStats, DatabaseStats, EpochStats};
+
+pub const COL_NUM: u32 = 10;
 pub const COL_MISC: u32 = 0;
 pub const COL_SLICE: u32 = 1;
 pub const COL_QUORUM: u32 = 2;
 pub const COL_QUORUM_NUM: u32 = 3;
 pub const COL_BLOB_STATUS: u32 = 4;
+pub const COL_ERASURE_COMMITMENT: u32 = 5;
+pub const COL_REGISTRATION: u32 = 6;
+pub const COL_TRANSACTION: u32 = 7;
+pub const COL_BLOB_INDEX: u32 = 8;
+pub const COL_SLICE_DATA: u32 = 9;
+// `COL_SLICE`, `COL_BLOB_INDEX` and `COL_SLICE_DATA` are only read by migrations since schema
+// version 3, which moved slices to per-epoch partitions
+
+const DEFAULT_EPOCHS_PER_PARTITION: u64 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct StorageConfig {
+    // migrate databases of an older schema on open instead of refusing them
+    pub migrate: bool,
+    // RocksDB settings of the blob index, light slice and row data columns of each partition
+    pub blob_index: ColumnConfig,
+    pub slice: ColumnConfig,
+    pub slice_data: ColumnConfig,
+    // epochs of slices stored together, and removed together once all of them are pruned
+    pub epochs_per_partition: Option<u64>,
+}
 
 pub struct Storage {
-    db: Arc<Database>,
+    db: Arc<dyn KeyValueDB>,
+    // blob indices, light slices and row data, by epoch
+    partitions: Arc<SlicePartitions>,
 }
 
 impl Storage {
-    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
-        let mut db_config = DatabaseConfig::with_columns(COL_NUM);
-        db_config.enable_statistics = true;
-        let db = Arc::new(Database::open(&db_config, path)?);
-        Ok(Storage { db })
+    /// Opens the RocksDB databases in `path`.
+    pub fn new(path: impl AsRef<Path>, config: &StorageConfig) -> Result<Self> {
+        let storage = Self::with_backend(Arc::new(RocksDbBackend::open(path, config)?), config)?;
+        // partitions pruned while still read when the node stopped are removed now
+        if let Some(epoch) = misc_db::prune_progress(&*storage.db)? {
+            storage.partitions.prune(epoch);
+        }
+        Ok(storage)
+    }
+
+    /// Opens the RocksDB databases in `path` for reading only, alongside the node if it runs.
+    pub fn open_read_only(path: impl AsRef<Path>, config: &StorageConfig) -> Result<Self> {
+        let config = StorageConfig {
+            migrate: false,
+            ..config.clone()
+        };
+        Self::with_backend(
+            Arc::new(RocksDbBackend::open_read_only(path, &config)?),
+            &config,
+        )
+    }
+
+    /// Empty storage in memory, for tests.
+    pub fn in_memory() -> Self {
+        Self::with_backend(
+            Arc::new(MemoryBackend::default()),
+            &StorageConfig::default(),
+        )
+        .expect("new in-memory storage")
+    }
+
+    pub fn with_backend(backend: Arc<dyn StorageBackend>, config: &StorageConfig) -> Result<Self> {
+        let db = backend.main();
+        let partitions = Arc::new(SlicePartitions::open(
+            backend,
+            config
+                .epochs_per_partition
+                .unwrap_or(DEFAULT_EPOCHS_PER_PARTITION),
+        )?);
+        schema::check_schema(&*db, &partitions, config.migrate)?;
+        Ok(Storage { db, partitions })
+    }
+
+    /// Migrates the database to `SCHEMA_VERSION`, returning the version it was at.
+    pub fn migrate(path: impl AsRef<Path>, config: &StorageConfig) -> Result<u64> {
+        let backend = Arc::new(RocksDbBackend::open(path, config)?);
+        let db = backend.main();
+        let partitions = SlicePartitions::open(
+            backend,
+            config
+                .epochs_per_partition
+                .unwrap_or(DEFAULT_EPOCHS_PER_PARTITION),
+        )?;
+        schema::check_schema(&*db, &partitions, true)
     }
 }
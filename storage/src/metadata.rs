@@ -0,0 +1,184 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    blob_status_db::{get_blob_key, BlobStatus},
+    misc_db::MiscDB,
+    quorum_db::{AssignedSlices, QuorumDB},
+    Storage, COL_BLOB_STATUS, COL_QUORUM_NUM,
+};
+
+/// Version of the metadata export format written by this build.
+pub const METADATA_FORMAT_VERSION: u64 = 1;
+
+// rows imported in one transaction
+const IMPORT_BATCH_SIZE: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedBlob {
+    pub epoch: u64,
+    pub quorum_id: u64,
+    pub storage_root: [u8; 32],
+    pub status: BlobStatus,
+}
+
+/// Blob statuses, quorums and progress of a node, from which a new node syncs on.
+///
+/// Erasure commitments are left out, as they record what the exporting node signed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetadataExport {
+    pub format_version: u64,
+    // next block to sync DA events from, all events before it are reflected in `blobs`
+    pub block_number: u64,
+    // last pruned epoch
+    pub prune_progress: Option<u64>,
+    pub blobs: Vec<ExportedBlob>,
+    // the slices assigned to the exporting signer in each quorum, by epoch
+    pub quorums: Vec<(u64, Vec<AssignedSlices>)>,
+}
+
+impl MetadataExport {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let export: MetadataExport = bincode::deserialize(&fs::read(path)?)?;
+        if export.format_version != METADATA_FORMAT_VERSION {
+            bail!(
+                "metadata export format version {} is not the supported version {}",
+                export.format_version,
+                METADATA_FORMAT_VERSION
+            );
+        }
+        Ok(export)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, bincode::serialize(self)?)?;
+        Ok(())
+    }
+}
+
+fn parse_u64(raw: &[u8]) -> Result<u64> {
+    Ok(u64::from_be_bytes(
+        raw.try_into().map_err(|_| anyhow!("invalid u64 value"))?,
+    ))
+}
+
+impl Storage {
+    pub async fn export_metadata(&self) -> Result<MetadataExport> {
+        let block_number = self
+            .get_sync_progress()
+            .await?
+            .ok_or_else(|| anyhow!("no DA events synced yet"))?;
+        let prune_progress = self.get_prune_progress().await?;
+
+        let mut blobs = vec![];
+        for item in self.db.iter(COL_BLOB_STATUS) {
+            let (key, value) = item?;
+            if key.len() != 48 {
+                bail!("invalid blob status key");
+            }
+            blobs.push(ExportedBlob {
+                epoch: parse_u64(&key[..8])?,
+                quorum_id: parse_u64(&key[8..16])?,
+                storage_root: key[16..].try_into().unwrap(),
+                status: parse_u64(&value)?
+                    .try_into()
+                    .map_err(|_| anyhow!("error when convert u64 to BlobStatus"))?,
+            });
+        }
+
+        let mut quorums = vec![];
+        for item in self.db.iter(COL_QUORUM_NUM) {
+            let (key, value) = item?;
+            let epoch = parse_u64(&key)?;
+            let mut assigned = vec![];
+            for quorum_id in 0..parse_u64(&value)? {
+                assigned.push(
+                    self.get_assgined_slices(epoch, quorum_id)
+                        .await?
+                        .ok_or_else(|| {
+                            anyhow!("quorum {} of epoch {} missing", quorum_id, epoch)
+                        })?,
+                );
+            }
+            quorums.push((epoch, assigned));
+        }
+
+        Ok(MetadataExport {
+            format_version: METADATA_FORMAT_VERSION,
+            block_number,
+            prune_progress,
+            blobs,
+            quorums,
+        })
+    }
+
+    /// Writes the exported metadata to a node that has not synced any DA events yet.
+    ///
+    /// The sync progress is written last, so an interrupted import is retried from scratch.
+    pub async fn import_metadata(&self, export: &MetadataExport, quorums: bool) -> Result<()> {
+        if self.get_sync_progress().await?.is_some() {
+            bail!("cannot import metadata into a node that already synced DA events");
+        }
+        for batch in export.blobs.chunks(IMPORT_BATCH_SIZE) {
+            let mut tx = self.db.transaction();
+            for blob in batch {
+                tx.put(
+                    COL_BLOB_STATUS,
+                    &get_blob_key(blob.epoch, blob.quorum_id, blob.storage_root),
+                    &(blob.status as u64).to_be_bytes(),
+                );
+            }
+            self.db.write(tx)?;
+        }
+        if quorums {
+            for (epoch, assigned) in &export.quorums {
+                self.put_quorums(*epoch, assigned.clone()).await?;
+            }
+        }
+        if let Some(epoch) = export.prune_progress {
+            self.put_prune_progress(epoch).await?;
+        }
+        self.put_sync_progress(export.block_number).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_status_db::BlobStatusDB;
+
+    #[tokio::test]
+    async fn metadata_export_test() {
+        let storage = Storage::in_memory();
+        storage
+            .put_blob(3, 1, [7; 32], BlobStatus::VERIFIED)
+            .await
+            .unwrap();
+        storage
+            .put_quorums(3, vec![AssignedSlices(vec![1, 2]), AssignedSlices(vec![])])
+            .await
+            .unwrap();
+        assert!(storage.export_metadata().await.is_err());
+        storage.put_sync_progress(100).await.unwrap();
+
+        let export = storage.export_metadata().await.unwrap();
+        assert_eq!(export.block_number, 100);
+        assert_eq!(
+            export.blobs,
+            vec![ExportedBlob {
+                epoch: 3,
+                quorum_id: 1,
+                storage_root: [7; 32],
+                status: BlobStatus::VERIFIED,
+            }]
+        );
+
+        let imported = Storage::in_memory();
+        imported.import_metadata(&export, true).await.unwrap();
+        assert_eq!(imported.export_metadata().await.unwrap(), export);
+        // nodes that synced already are refused
+        assert!(imported.import_metadata(&export, true).await.is_err());
+    }
+}
@@ -3,6 +3,7 @@ use crate::COL_MISC;
 use super::Storage;
 use anyhow::Result;
 use async_trait::async_trait;
+use kvdb::KeyValueDB;
 
 const SYNC_PROGRESS_KEY: &[u8] = &[0];
 const PRUNE_PROGRESS_KEY: &[u8] = &[1];
@@ -42,9 +43,13 @@ impl MiscDB for Storage {
     }
 
     async fn get_prune_progress(&self) -> Result<Option<u64>> {
-        if let Some(raw_data) = self.db.get(COL_MISC, PRUNE_PROGRESS_KEY)? {
-            return Ok(Some(u64::from_be_bytes(raw_data.try_into().unwrap())));
-        }
-        Ok(None)
+        prune_progress(&*self.db)
+    }
+}
+
+pub(crate) fn prune_progress(db: &dyn KeyValueDB) -> Result<Option<u64>> {
+    if let Some(raw_data) = db.get(COL_MISC, PRUNE_PROGRESS_KEY)? {
+        return Ok(Some(u64::from_be_bytes(raw_data.try_into().unwrap())));
     }
+    Ok(None)
 }
@@ -0,0 +1,301 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use anyhow::{anyhow, Result};
+use kvdb::{DBKeyValue, DBTransaction, DBValue, KeyValueDB};
+use tracing::{info, warn};
+
+use crate::{StorageBackend, COL_BLOB_INDEX, COL_SLICE, COL_SLICE_DATA};
+
+// columns of a partition database, with the same keys as the columns of the main database
+// they replace
+pub(crate) const PARTITION_COL_BLOB_INDEX: u32 = 0;
+pub(crate) const PARTITION_COL_SLICE: u32 = 1;
+pub(crate) const PARTITION_COL_SLICE_DATA: u32 = 2;
+// checksums of the light slice and row data, keyed as `PARTITION_COL_SLICE_DATA`
+pub(crate) const PARTITION_COL_CHECKSUM: u32 = 3;
+// slices found corrupted, kept for inspection until pruned
+pub(crate) const PARTITION_COL_QUARANTINE: u32 = 4;
+pub(crate) const PARTITION_COL_NUM: u32 = 5;
+
+// rows moved to the partitions in one transaction by the migration
+const MIGRATION_BATCH_SIZE: usize = 10000;
+
+/// A partition database, whose files are removed once it is pruned and its last handle drops,
+/// so that pruning never removes a partition still being read or copied.
+pub(crate) struct PartitionDb {
+    name: String,
+    // taken on drop to close the database before removing it
+    db: Option<Arc<dyn KeyValueDB>>,
+    backend: Arc<dyn StorageBackend>,
+    pruned: AtomicBool,
+}
+
+impl PartitionDb {
+    fn db(&self) -> &dyn KeyValueDB {
+        &**self
+            .db
+            .as_ref()
+            .expect("partition database is open until dropped")
+    }
+}
+
+impl Drop for PartitionDb {
+    fn drop(&mut self) {
+        // the RocksDB backend only keeps weak handles, so this closes the database before removal
+        drop(self.db.take());
+        if self.pruned.load(Ordering::Acquire) {
+            info!("removing pruned slice partition {:?}", self.name);
+            if let Err(e) = self.backend.remove_partition(&self.name) {
+                warn!("failed to remove slice partition {:?}: {:?}", self.name, e);
+            }
+        }
+    }
+}
+
+impl KeyValueDB for PartitionDb {
+    fn get(&self, col: u32, key: &[u8]) -> io::Result<Option<DBValue>> {
+        self.db().get(col, key)
+    }
+
+    fn get_by_prefix(&self, col: u32, prefix: &[u8]) -> io::Result<Option<DBValue>> {
+        self.db().get_by_prefix(col, prefix)
+    }
+
+    fn write(&self, transaction: DBTransaction) -> io::Result<()> {
+        self.db().write(transaction)
+    }
+
+    fn iter<'a>(&'a self, col: u32) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        self.db().iter(col)
+    }
+
+    fn iter_with_prefix<'a>(
+        &'a self,
+        col: u32,
+        prefix: &'a [u8],
+    ) -> Box<dyn Iterator<Item = io::Result<DBKeyValue>> + 'a> {
+        self.db().iter_with_prefix(col, prefix)
+    }
+}
+
+struct Partition {
+    last_epoch: u64,
+    db: Arc<PartitionDb>,
+}
+
+pub(crate) type NamedPartitions = Vec<(String, Arc<PartitionDb>)>;
+
+fn partition_name(first_epoch: u64, last_epoch: u64) -> String {
+    format!("{}-{}", first_epoch, last_epoch)
+}
+
+/// Slice data split into one database per range of epochs, so that pruning a range removes
+/// its files at once instead of leaving tombstones to compaction.
+pub(crate) struct SlicePartitions {
+    backend: Arc<dyn StorageBackend>,
+    epochs_per_partition: u64,
+    // keyed by the first epoch of each partition
+    partitions: RwLock<BTreeMap<u64, Partition>>,
+}
+
+impl SlicePartitions {
+    /// Opens the partitions of the backend, which are named `<first epoch>-<last epoch>`.
+    pub fn open(backend: Arc<dyn StorageBackend>, epochs_per_partition: u64) -> Result<Self> {
+        let mut partitions = BTreeMap::new();
+        for name in backend.partitions()? {
+            let (first_epoch, last_epoch) = name
+                .split_once('-')
+                .and_then(|(first, last)| Some((first.parse().ok()?, last.parse().ok()?)))
+                .ok_or_else(|| anyhow!("unexpected entry {:?} in slice partitions", name))?;
+            let db = open_partition(&backend, name)?;
+            partitions.insert(first_epoch, Partition { last_epoch, db });
+        }
+        Ok(Self {
+            backend,
+            epochs_per_partition: epochs_per_partition.max(1),
+            partitions: RwLock::new(partitions),
+        })
+    }
+
+    pub fn backend(&self) -> &Arc<dyn StorageBackend> {
+        &self.backend
+    }
+
+    /// The partition holding `epoch`, if it is created.
+    pub fn get(&self, epoch: u64) -> Option<Arc<PartitionDb>> {
+        let partitions = self.partitions.read().unwrap();
+        match partitions.range(..=epoch).next_back() {
+            Some((_, partition)) if epoch <= partition.last_epoch => Some(partition.db.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_or_create(&self, epoch: u64) -> Result<Arc<PartitionDb>> {
+        if let Some(db) = self.get(epoch) {
+            return Ok(db);
+        }
+        let mut partitions = self.partitions.write().unwrap();
+        // partitions keep the range they were created with if `epochs_per_partition` changes
+        let previous_last_epoch = partitions
+            .range(..=epoch)
+            .next_back()
+            .map(|(_, partition)| partition.last_epoch);
+        if previous_last_epoch.is_some_and(|last_epoch| epoch <= last_epoch) {
+            // created concurrently
+            return Ok(partitions.range(..=epoch).next_back().unwrap().1.db.clone());
+        }
+        let first_epoch = (epoch - epoch % self.epochs_per_partition)
+            .max(previous_last_epoch.map_or(0, |last_epoch| last_epoch + 1));
+        let last_epoch = match partitions.range(epoch + 1..).next() {
+            Some((next_first_epoch, _)) => {
+                (first_epoch + self.epochs_per_partition - 1).min(next_first_epoch - 1)
+            }
+            None => first_epoch + self.epochs_per_partition - 1,
+        };
+        info!(
+            "creating slice partition of epochs {:?} to {:?}",
+            first_epoch, last_epoch
+        );
+        let db = open_partition(&self.backend, partition_name(first_epoch, last_epoch))?;
+        partitions.insert(
+            first_epoch,
+            Partition {
+                last_epoch,
+                db: db.clone(),
+            },
+        );
+        Ok(db)
+    }
+
+    /// The first and last epoch of the partitions, if any.
+    pub fn epoch_range(&self) -> Option<(u64, u64)> {
+        let partitions = self.partitions.read().unwrap();
+        let (first_epoch, _) = partitions.first_key_value()?;
+        let (_, last) = partitions.last_key_value()?;
+        Some((*first_epoch, last.last_epoch))
+    }
+
+    /// The names of the partitions with the epochs each holds.
+    pub fn epoch_ranges(&self) -> Vec<(String, RangeInclusive<u64>)> {
+        self.partitions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(first_epoch, partition)| {
+                (
+                    partition_name(*first_epoch, partition.last_epoch),
+                    *first_epoch..=partition.last_epoch,
+                )
+            })
+            .collect()
+    }
+
+    /// The partitions by name, kept on disk while their handles are held even if pruned.
+    pub fn snapshot(&self) -> NamedPartitions {
+        self.partitions
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(first_epoch, partition)| {
+                (
+                    partition_name(*first_epoch, partition.last_epoch),
+                    partition.db.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Drops the partitions whose epochs are all at or before `epoch`, which are removed once
+    /// no longer read.
+    pub fn prune(&self, epoch: u64) {
+        let mut partitions = self.partitions.write().unwrap();
+        let pruned: Vec<u64> = partitions
+            .iter()
+            .filter(|(_, partition)| partition.last_epoch <= epoch)
+            .map(|(first_epoch, _)| *first_epoch)
+            .collect();
+        for first_epoch in pruned {
+            let partition = partitions.remove(&first_epoch).unwrap();
+            info!(
+                "pruning slice partition of epochs {:?} to {:?}",
+                first_epoch, partition.last_epoch
+            );
+            partition.db.pruned.store(true, Ordering::Release);
+        }
+    }
+}
+
+fn open_partition(backend: &Arc<dyn StorageBackend>, name: String) -> Result<Arc<PartitionDb>> {
+    Ok(Arc::new(PartitionDb {
+        db: Some(backend.open_partition(&name)?),
+        name,
+        backend: backend.clone(),
+        pruned: AtomicBool::new(false),
+    }))
+}
+
+/// Moves slices from the columns of the main database into the partitions of their epochs.
+pub(crate) fn move_slices_to_partitions(
+    db: &dyn KeyValueDB,
+    partitions: &SlicePartitions,
+) -> Result<()> {
+    // main column, partition column and the offset of the epoch in keys
+    for (col, partition_col, epoch_offset) in [
+        (COL_BLOB_INDEX, PARTITION_COL_BLOB_INDEX, 0),
+        (COL_SLICE, PARTITION_COL_SLICE, 1),
+        (COL_SLICE_DATA, PARTITION_COL_SLICE_DATA, 0),
+    ] {
+        let mut pending: Vec<(Vec<u8>, Vec<u8>)> = vec![];
+        let mut pending_epoch = None;
+        for item in db.iter(col) {
+            let (key, value) = item?;
+            let epoch = u64::from_be_bytes(
+                key.get(epoch_offset..epoch_offset + 8)
+                    .and_then(|x| x.try_into().ok())
+                    .ok_or_else(|| anyhow!("Incorrect key format"))?,
+            );
+            if pending_epoch != Some(epoch) || pending.len() == MIGRATION_BATCH_SIZE {
+                if let Some(pending_epoch) = pending_epoch {
+                    write_moved(db, col, partitions, pending_epoch, partition_col, &pending)?;
+                }
+                pending.clear();
+                pending_epoch = Some(epoch);
+            }
+            pending.push((key.to_vec(), value));
+        }
+        if let Some(pending_epoch) = pending_epoch {
+            write_moved(db, col, partitions, pending_epoch, partition_col, &pending)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_moved(
+    db: &dyn KeyValueDB,
+    col: u32,
+    partitions: &SlicePartitions,
+    epoch: u64,
+    partition_col: u32,
+    rows: &[(Vec<u8>, Vec<u8>)],
+) -> Result<()> {
+    let partition = partitions.get_or_create(epoch)?;
+    let mut tx = partition.transaction();
+    for (key, value) in rows {
+        tx.put(partition_col, key, value);
+    }
+    partition.write(tx)?;
+    let mut tx = db.transaction();
+    for (key, _) in rows {
+        tx.delete(col, key);
+    }
+    db.write(tx)?;
+    Ok(())
+}
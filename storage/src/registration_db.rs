@@ -0,0 +1,144 @@
+use crate::COL_REGISTRATION;
+
+use super::Storage;
+use anyhow::Result;
+use async_trait::async_trait;
+use kvdb::KeyValueDB;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationKind {
+    Signer,
+    Epoch,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum RegistrationOutcome {
+    Success,
+    Reverted,
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RegistrationRecord {
+    pub kind: RegistrationKind,
+    // epoch registered for, or the current epoch for signer registration
+    pub epoch: u64,
+    // unix timestamp in seconds
+    pub timestamp: u64,
+    pub tx_hash: Option<[u8; 32]>,
+    pub block_number: Option<u64>,
+    pub signature: Vec<u8>,
+    pub outcome: RegistrationOutcome,
+}
+
+#[async_trait]
+pub trait RegistrationDB {
+    async fn put_registration_record(&self, record: RegistrationRecord) -> Result<()>;
+
+    async fn get_registration_records(
+        &self,
+        from_epoch: u64,
+        limit: usize,
+    ) -> Result<Vec<RegistrationRecord>>;
+}
+
+// epoch, timestamp and kind, followed by a sequence number among records sharing them
+fn get_registration_prefix(record: &RegistrationRecord) -> Vec<u8> {
+    record
+        .epoch
+        .to_be_bytes()
+        .into_iter()
+        .chain(record.timestamp.to_be_bytes())
+        .chain([record.kind as u8])
+        .collect()
+}
+
+#[async_trait]
+impl RegistrationDB for Storage {
+    async fn put_registration_record(&self, record: RegistrationRecord) -> Result<()> {
+        let prefix = get_registration_prefix(&record);
+        let seq = KeyValueDB::iter_with_prefix(&*self.db, COL_REGISTRATION, &prefix).count() as u32;
+        let mut tx = self.db.transaction();
+        tx.put(
+            COL_REGISTRATION,
+            &[prefix, seq.to_be_bytes().to_vec()].concat(),
+            &bincode::serialize(&record)?,
+        );
+        self.db.write(tx)?;
+        Ok(())
+    }
+
+    async fn get_registration_records(
+        &self,
+        from_epoch: u64,
+        limit: usize,
+    ) -> Result<Vec<RegistrationRecord>> {
+        let start = from_epoch.to_be_bytes();
+        self.partitions
+            .backend()
+            .iter_main_from(COL_REGISTRATION, &start)
+            .take(limit)
+            .map(|item| Ok(bincode::deserialize(&item?.1)?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(kind: RegistrationKind, epoch: u64, timestamp: u64) -> RegistrationRecord {
+        RegistrationRecord {
+            kind,
+            epoch,
+            timestamp,
+            tx_hash: None,
+            block_number: None,
+            signature: vec![],
+            outcome: RegistrationOutcome::Error("reverted".into()),
+        }
+    }
+
+    fn epochs(records: Vec<RegistrationRecord>) -> Vec<u64> {
+        records.into_iter().map(|r| r.epoch).collect()
+    }
+
+    #[tokio::test]
+    async fn registration_records_test() {
+        let storage = Storage::in_memory();
+        for epoch in [1, 255, 256, 300] {
+            storage
+                .put_registration_record(record(RegistrationKind::Epoch, epoch, 10))
+                .await
+                .unwrap();
+        }
+        // records of the same epoch and second are kept apart
+        storage
+            .put_registration_record(record(RegistrationKind::Epoch, 255, 10))
+            .await
+            .unwrap();
+        storage
+            .put_registration_record(record(RegistrationKind::Signer, 255, 10))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            epochs(storage.get_registration_records(0, 10).await.unwrap()),
+            vec![1, 255, 255, 255, 256, 300]
+        );
+        assert_eq!(
+            epochs(storage.get_registration_records(255, 10).await.unwrap()),
+            vec![255, 255, 255, 256, 300]
+        );
+        assert_eq!(
+            epochs(storage.get_registration_records(256, 1).await.unwrap()),
+            vec![256]
+        );
+        assert!(storage
+            .get_registration_records(301, 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
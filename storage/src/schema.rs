@@ -0,0 +1,207 @@
+use anyhow::{anyhow, bail, Result};
+use kvdb::KeyValueDB;
+use tracing::info;
+
+use crate::{
+    partition::{move_slices_to_partitions, SlicePartitions},
+    slice_db::split_slice_columns,
+    COL_MISC, COL_NUM,
+};
+
+const SCHEMA_VERSION_KEY: &[u8] = &[2];
+
+/// Version of the key layouts written by this build.
+///
+/// 0: databases created before versioning, without a version marker.
+/// 1: the version marker in `COL_MISC`.
+/// 2: blob indices and row data in `COL_BLOB_INDEX` and `COL_SLICE_DATA`.
+/// 3: slices in per-epoch partitions.
+/// 4: slice checksums and quarantined slices in their own partition columns.
+pub const SCHEMA_VERSION: u64 = 4;
+
+struct Migration {
+    // the version migrated from, upgrading to the next one
+    from: u64,
+    description: &'static str,
+    run: fn(&dyn KeyValueDB, &SlicePartitions) -> Result<()>,
+}
+
+// in version order, one for each version below `SCHEMA_VERSION`
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "add schema version marker",
+        run: |_, _| Ok(()),
+    },
+    Migration {
+        from: 1,
+        description: "move blob indices and row data out of the slice column",
+        run: |db, _| split_slice_columns(db),
+    },
+    Migration {
+        from: 2,
+        description: "move slices to per-epoch partitions",
+        run: move_slices_to_partitions,
+    },
+    Migration {
+        from: 3,
+        // slices without checksums are only deserialized when scrubbed
+        description: "add checksum and quarantine columns to the partitions",
+        run: |_, _| Ok(()),
+    },
+];
+
+pub(crate) fn get_schema_version(db: &dyn KeyValueDB) -> Result<Option<u64>> {
+    match db.get(COL_MISC, SCHEMA_VERSION_KEY)? {
+        Some(raw_data) => Ok(Some(u64::from_be_bytes(
+            raw_data
+                .try_into()
+                .map_err(|_| anyhow!("invalid schema version"))?,
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn put_schema_version(db: &dyn KeyValueDB, version: u64) -> Result<()> {
+    let mut tx = db.transaction();
+    tx.put(COL_MISC, SCHEMA_VERSION_KEY, &version.to_be_bytes());
+    db.write(tx)?;
+    Ok(())
+}
+
+fn is_empty(db: &dyn KeyValueDB) -> Result<bool> {
+    for col in 0..COL_NUM {
+        if db.iter(col).next().transpose()?.is_some() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Brings the database to `SCHEMA_VERSION`, returning the version it was at.
+///
+/// Older databases are migrated step by step if `migrate` is set and refused otherwise, and
+/// databases of a newer schema are always refused.
+pub(crate) fn check_schema(
+    db: &dyn KeyValueDB,
+    partitions: &SlicePartitions,
+    migrate: bool,
+) -> Result<u64> {
+    let version = match get_schema_version(db)? {
+        Some(version) => version,
+        None if is_empty(db)? => {
+            put_schema_version(db, SCHEMA_VERSION)?;
+            return Ok(SCHEMA_VERSION);
+        }
+        None => 0,
+    };
+    if version > SCHEMA_VERSION {
+        bail!(
+            "database schema version {} is newer than the supported version {}",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    if version < SCHEMA_VERSION && !migrate {
+        bail!(
+            "database schema version {} is older than {}, run the `migrate-db` command first",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version) {
+        info!(
+            "migrating database schema from version {} to {}: {}",
+            migration.from,
+            migration.from + 1,
+            migration.description
+        );
+        (migration.run)(db, partitions)?;
+        put_schema_version(db, migration.from + 1)?;
+    }
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::partition::{
+        PARTITION_COL_BLOB_INDEX, PARTITION_COL_SLICE, PARTITION_COL_SLICE_DATA,
+    };
+    use crate::{MemoryBackend, StorageBackend, COL_BLOB_INDEX, COL_SLICE, COL_SLICE_DATA};
+
+    fn open() -> (
+        Arc<dyn StorageBackend>,
+        Arc<dyn KeyValueDB>,
+        SlicePartitions,
+    ) {
+        let backend: Arc<dyn StorageBackend> = Arc::new(MemoryBackend::default());
+        let partitions = SlicePartitions::open(backend.clone(), 2).unwrap();
+        let db = backend.main();
+        (backend, db, partitions)
+    }
+
+    #[test]
+    fn check_schema_test() {
+        // new databases start at the current version
+        let (_, db, partitions) = open();
+        assert_eq!(
+            check_schema(&*db, &partitions, false).unwrap(),
+            SCHEMA_VERSION
+        );
+        assert_eq!(get_schema_version(&*db).unwrap(), Some(SCHEMA_VERSION));
+
+        // unversioned databases with data are migrated from version 0
+        let (backend, db, partitions) = open();
+        let key: Vec<u8> = 3u64.to_be_bytes().into_iter().chain([7]).collect();
+        let prefixed =
+            |prefix: u8| -> Vec<u8> { [prefix].into_iter().chain(key.clone()).collect() };
+        let mut tx = db.transaction();
+        tx.put(COL_MISC, &[0], &1u64.to_be_bytes());
+        tx.put(COL_SLICE, &prefixed(0), &[1]);
+        tx.put(COL_SLICE, &prefixed(1), &[2]);
+        tx.put(COL_SLICE, &prefixed(2), &[3]);
+        db.write(tx).unwrap();
+        assert!(check_schema(&*db, &partitions, false).is_err());
+        assert_eq!(check_schema(&*db, &partitions, true).unwrap(), 0);
+        assert_eq!(get_schema_version(&*db).unwrap(), Some(SCHEMA_VERSION));
+        for col in [COL_SLICE, COL_BLOB_INDEX, COL_SLICE_DATA] {
+            assert!(db.iter(col).next().is_none());
+        }
+        // epoch 3 is in the partition of epochs 2 to 3
+        assert!(partitions.get(1).is_none());
+        let partition = partitions.get(3).unwrap();
+        assert_eq!(
+            partition.get(PARTITION_COL_BLOB_INDEX, &key).unwrap(),
+            Some(vec![1])
+        );
+        assert_eq!(
+            partition.get(PARTITION_COL_SLICE, &prefixed(1)).unwrap(),
+            Some(vec![2])
+        );
+        assert_eq!(
+            partition.get(PARTITION_COL_SLICE_DATA, &key).unwrap(),
+            Some(vec![3])
+        );
+
+        // pruning removes a partition once all of its epochs are pruned
+        partitions.prune(2);
+        assert!(partitions.get(3).is_some());
+        partitions.prune(3);
+        assert!(partitions.get(3).is_none());
+        // and its last handle drops
+        assert_eq!(
+            partition.get(PARTITION_COL_SLICE_DATA, &key).unwrap(),
+            Some(vec![3])
+        );
+        assert_eq!(backend.partitions().unwrap(), vec!["2-3"]);
+        drop(partition);
+        assert!(backend.partitions().unwrap().is_empty());
+
+        // newer databases are refused
+        put_schema_version(&*db, SCHEMA_VERSION + 1).unwrap();
+        assert!(check_schema(&*db, &partitions, true).is_err());
+    }
+}
@@ -1,6 +1,12 @@
 use std::{collections::BTreeSet, iter::once};
 
-use crate::COL_SLICE;
+use crate::{
+    partition::{
+        PARTITION_COL_BLOB_INDEX, PARTITION_COL_CHECKSUM, PARTITION_COL_QUARANTINE,
+        PARTITION_COL_SLICE, PARTITION_COL_SLICE_DATA,
+    },
+    COL_BLOB_INDEX, COL_SLICE, COL_SLICE_DATA,
+};
 
 use super::Storage;
 use anyhow::{bail, Result};
@@ -24,21 +30,46 @@ pub struct BlobInfo {
     pub indicies: Vec<u16>,
 }
 
-const BLOB_PREFIX: u8 = 0;
+/// Result of reading a slice back for scrubbing.
+#[derive(Debug)]
+pub enum SliceCheck {
+    Missing,
+    // the stored bytes mismatch their checksum or cannot be deserialized
+    Corrupted(String),
+    Intact {
+        slice: Box<LightEncodedSlice>,
+        row: Vec<[u8; 32]>,
+        // slices written before checksums were added are only deserialized
+        checksummed: bool,
+    },
+}
+
+// blob indices and row data shared `COL_SLICE` with light slices under these prefixes before
+// schema version 2, light slices keep their prefix
+const LEGACY_BLOB_PREFIX: u8 = 0;
 const SLICE_PREFIX: u8 = 1;
-const DATA_PREFIX: u8 = 2;
+const LEGACY_DATA_PREFIX: u8 = 2;
+// rows moved to the new columns in one transaction by the migration
+const MIGRATION_BATCH_SIZE: usize = 10000;
+
+fn blob_key(epoch: u64, quorum_id: u64, storage_root: [u8; 32]) -> Vec<u8> {
+    epoch
+        .to_be_bytes()
+        .into_iter()
+        .chain(quorum_id.to_be_bytes())
+        .chain(storage_root)
+        .collect()
+}
 
 impl SliceIndex {
     fn to_slice_key(&self) -> Vec<u8> {
-        self.to_key_with_prefix(SLICE_PREFIX)
-    }
-    fn to_data_key(&self) -> Vec<u8> {
-        self.to_key_with_prefix(DATA_PREFIX)
+        once(SLICE_PREFIX).chain(self.to_data_key()).collect()
     }
 
-    fn to_key_with_prefix(&self, prefix: u8) -> Vec<u8> {
-        once(prefix)
-            .chain(self.epoch.to_be_bytes())
+    fn to_data_key(&self) -> Vec<u8> {
+        self.epoch
+            .to_be_bytes()
+            .into_iter()
             .chain(self.quorum_id.to_be_bytes())
             .chain(self.storage_root)
             .chain(self.index.to_be_bytes())
@@ -82,9 +113,41 @@ pub trait SliceDB {
 
     async fn get_epoch_info(&self, epoch: u64) -> Result<BTreeSet<BlobInfo>>;
 
+    /// The first and last epoch that may have slices stored.
+    async fn get_epoch_range(&self) -> Result<Option<(u64, u64)>>;
+
+    /// Reads a slice and its row with validation, checking them against their checksum.
+    async fn check_slice(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        index: usize,
+    ) -> Result<SliceCheck>;
+
+    /// Moves a slice out of reach of reads, keeping its bytes until the epoch is pruned.
+    ///
+    /// Its index is dropped from the blob, so that mining and scrubbing skip it, while retrieving
+    /// its row reports the slice missing.
+    async fn quarantine_slice(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        index: usize,
+    ) -> Result<()>;
+
     async fn prune(&self, epoch: u64) -> Result<()>;
 }
 
+fn slice_checksum(light_slice: &[u8], data: &[u8]) -> Vec<u8> {
+    crc32fast::hash(light_slice)
+        .to_be_bytes()
+        .into_iter()
+        .chain(crc32fast::hash(data).to_be_bytes())
+        .collect()
+}
+
 #[async_trait]
 impl SliceDB for Storage {
     async fn get_raw_slice(
@@ -100,10 +163,9 @@ impl SliceDB for Storage {
             storage_root,
             index: index as u64,
         };
-        if let Some(slice) = self.db.get(COL_SLICE, &index.to_data_key())? {
-            Ok(Some(slice))
-        } else {
-            Ok(None)
+        match self.partitions.get(epoch) {
+            Some(partition) => Ok(partition.get(PARTITION_COL_SLICE_DATA, &index.to_data_key())?),
+            None => Ok(None),
         }
     }
 
@@ -140,7 +202,10 @@ impl SliceDB for Storage {
             storage_root,
             index: index as u64,
         };
-        let raw_slice = if let Some(slice) = self.db.get(COL_SLICE, &index.to_slice_key())? {
+        let raw_slice = if let Some(slice) = match self.partitions.get(epoch) {
+            Some(partition) => partition.get(PARTITION_COL_SLICE, &index.to_slice_key())?,
+            None => None,
+        } {
             slice
         } else {
             return Ok(None);
@@ -166,17 +231,18 @@ impl SliceDB for Storage {
         storage_root: [u8; 32],
         slices: Vec<EncodedSlice>,
     ) -> Result<()> {
-        let mut tx = self.db.transaction();
+        let partition = self.partitions.get_or_create(epoch)?;
+        let mut tx = partition.transaction();
 
-        let blob_key: Vec<u8> = once(BLOB_PREFIX)
-            .chain(epoch.to_be_bytes())
-            .chain(quorum_id.to_be_bytes())
-            .chain(storage_root)
-            .collect();
+        let blob_key = blob_key(epoch, quorum_id, storage_root);
 
         // TODO: should we consider the update logic here?
         let indicies: Vec<u16> = slices.iter().map(|slice| slice.index as u16).collect();
-        tx.put(COL_SLICE, &blob_key, &bcs::to_bytes(&indicies).unwrap());
+        tx.put(
+            PARTITION_COL_BLOB_INDEX,
+            &blob_key,
+            &bcs::to_bytes(&indicies).unwrap(),
+        );
 
         for slice in slices.into_iter() {
             let index = SliceIndex {
@@ -188,31 +254,40 @@ impl SliceDB for Storage {
             let data = slice.merkle_row();
             let light_slice = slice.into_light_slice();
 
-            let mut value: Vec<u8> = Vec::new();
+            let mut light_value: Vec<u8> = Vec::new();
             // Note: Slice is stored in compressed form
-            light_slice.serialize_compressed(&mut value).unwrap();
-            tx.put(COL_SLICE, &index.to_slice_key(), &value);
+            light_slice.serialize_compressed(&mut light_value).unwrap();
+            tx.put(PARTITION_COL_SLICE, &index.to_slice_key(), &light_value);
 
             let mut value: Vec<u8> = Vec::new();
             data.serialize_uncompressed(&mut value).unwrap();
-            tx.put(COL_SLICE, &index.to_data_key(), &value);
+            tx.put(PARTITION_COL_SLICE_DATA, &index.to_data_key(), &value);
+            tx.put(
+                PARTITION_COL_CHECKSUM,
+                &index.to_data_key(),
+                &slice_checksum(&light_value, &value),
+            );
         }
 
-        self.db.write(tx)?;
+        partition.write(tx)?;
         Ok(())
     }
 
     async fn get_epoch_info(&self, epoch: u64) -> Result<BTreeSet<BlobInfo>> {
-        let prefix: Vec<u8> = once(BLOB_PREFIX).chain(epoch.to_be_bytes()).collect();
+        let prefix = epoch.to_be_bytes();
 
         let mut answer = BTreeSet::new();
+        let partition = match self.partitions.get(epoch) {
+            Some(partition) => partition,
+            None => return Ok(answer),
+        };
 
-        for item in KeyValueDB::iter_with_prefix(&*self.db, COL_SLICE, &prefix) {
+        for item in KeyValueDB::iter_with_prefix(&*partition, PARTITION_COL_BLOB_INDEX, &prefix) {
             let (key, value) = item?;
-            if key.len() != 1 + 8 + 8 + 32 {
+            if key.len() != 8 + 8 + 32 {
                 bail!("Incorrect key format");
             }
-            let mut key_slice = &key.as_ref()[9..];
+            let mut key_slice = &key.as_ref()[8..];
 
             let quorum_id = {
                 let (cur, rest) = key_slice.split_first_chunk::<8>().unwrap();
@@ -237,17 +312,287 @@ impl SliceDB for Storage {
         Ok(answer)
     }
 
-    async fn prune(&self, epoch: u64) -> Result<()> {
-        let blob_prefix: Vec<u8> = once(BLOB_PREFIX).chain(epoch.to_be_bytes()).collect();
-        let slice_prefix: Vec<u8> = once(SLICE_PREFIX).chain(epoch.to_be_bytes()).collect();
-        let data_prefix: Vec<u8> = once(DATA_PREFIX).chain(epoch.to_be_bytes()).collect();
+    async fn get_epoch_range(&self) -> Result<Option<(u64, u64)>> {
+        Ok(self.partitions.epoch_range())
+    }
+
+    async fn check_slice(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        index: usize,
+    ) -> Result<SliceCheck> {
+        let partition = match self.partitions.get(epoch) {
+            Some(partition) => partition,
+            None => return Ok(SliceCheck::Missing),
+        };
+        let index = SliceIndex {
+            epoch,
+            quorum_id,
+            storage_root,
+            index: index as u64,
+        };
+        let (light_value, value) = match (
+            partition.get(PARTITION_COL_SLICE, &index.to_slice_key())?,
+            partition.get(PARTITION_COL_SLICE_DATA, &index.to_data_key())?,
+        ) {
+            (Some(light_value), Some(value)) => (light_value, value),
+            (None, None) => return Ok(SliceCheck::Missing),
+            (None, Some(_)) => return Ok(SliceCheck::Corrupted("light slice missing".into())),
+            (Some(_), None) => return Ok(SliceCheck::Corrupted("row data missing".into())),
+        };
+        let checksum = partition.get(PARTITION_COL_CHECKSUM, &index.to_data_key())?;
+        if let Some(checksum) = &checksum {
+            if *checksum != slice_checksum(&light_value, &value) {
+                return Ok(SliceCheck::Corrupted("checksum mismatch".into()));
+            }
+        }
+        let slice = match LightEncodedSlice::deserialize_with_mode(
+            &*light_value,
+            ark_serialize::Compress::Yes,
+            ark_serialize::Validate::Yes,
+        ) {
+            Ok(slice) if slice.index as u64 == index.index => slice,
+            Ok(slice) => {
+                return Ok(SliceCheck::Corrupted(format!(
+                    "light slice of index {}",
+                    slice.index
+                )))
+            }
+            Err(e) => return Ok(SliceCheck::Corrupted(format!("light slice: {:?}", e))),
+        };
+        let row = match Vec::<[u8; 32]>::deserialize_uncompressed(&*value) {
+            Ok(row) => row,
+            Err(e) => return Ok(SliceCheck::Corrupted(format!("row data: {:?}", e))),
+        };
+        Ok(SliceCheck::Intact {
+            slice: Box::new(slice),
+            row,
+            checksummed: checksum.is_some(),
+        })
+    }
 
-        let mut tx = self.db.transaction();
-        tx.delete_prefix(COL_SLICE, &blob_prefix);
-        tx.delete_prefix(COL_SLICE, &slice_prefix);
-        tx.delete_prefix(COL_SLICE, &data_prefix);
+    async fn quarantine_slice(
+        &self,
+        epoch: u64,
+        quorum_id: u64,
+        storage_root: [u8; 32],
+        index: usize,
+    ) -> Result<()> {
+        let partition = match self.partitions.get(epoch) {
+            Some(partition) => partition,
+            None => return Ok(()),
+        };
+        let index = SliceIndex {
+            epoch,
+            quorum_id,
+            storage_root,
+            index: index as u64,
+        };
+        let quarantined = (
+            partition.get(PARTITION_COL_SLICE, &index.to_slice_key())?,
+            partition.get(PARTITION_COL_SLICE_DATA, &index.to_data_key())?,
+            partition.get(PARTITION_COL_CHECKSUM, &index.to_data_key())?,
+        );
+        let mut tx = partition.transaction();
+        let blob_key = blob_key(epoch, quorum_id, storage_root);
+        if let Some(value) = partition.get(PARTITION_COL_BLOB_INDEX, &blob_key)? {
+            let mut indicies: Vec<u16> = bcs::from_bytes(&value)?;
+            indicies.retain(|&i| i as u64 != index.index);
+            if indicies.is_empty() {
+                tx.delete(PARTITION_COL_BLOB_INDEX, &blob_key);
+            } else {
+                tx.put(
+                    PARTITION_COL_BLOB_INDEX,
+                    &blob_key,
+                    &bcs::to_bytes(&indicies)?,
+                );
+            }
+        }
+        tx.put(
+            PARTITION_COL_QUARANTINE,
+            &index.to_data_key(),
+            &bcs::to_bytes(&quarantined)?,
+        );
+        tx.delete(PARTITION_COL_SLICE, &index.to_slice_key());
+        tx.delete(PARTITION_COL_SLICE_DATA, &index.to_data_key());
+        tx.delete(PARTITION_COL_CHECKSUM, &index.to_data_key());
+        partition.write(tx)?;
+        Ok(())
+    }
 
-        self.db.write(tx)?;
+    async fn prune(&self, epoch: u64) -> Result<()> {
+        self.partitions.prune(epoch);
         Ok(())
     }
 }
+
+/// Moves blob indices and row data out of `COL_SLICE` into their own columns, dropping the prefix.
+pub(crate) fn split_slice_columns(db: &dyn KeyValueDB) -> Result<()> {
+    for (prefix, col) in [
+        (LEGACY_BLOB_PREFIX, COL_BLOB_INDEX),
+        (LEGACY_DATA_PREFIX, COL_SLICE_DATA),
+    ] {
+        let mut tx = db.transaction();
+        let mut batch = 0;
+        for item in KeyValueDB::iter_with_prefix(db, COL_SLICE, &[prefix]) {
+            let (key, value) = item?;
+            tx.put(col, &key[1..], &value);
+            tx.delete(COL_SLICE, &key);
+            batch += 1;
+            if batch == MIGRATION_BATCH_SIZE {
+                db.write(std::mem::take(&mut tx))?;
+                batch = 0;
+            }
+        }
+        db.write(tx)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: [u8; 32] = [7; 32];
+
+    fn slice_index(index: u64) -> SliceIndex {
+        SliceIndex {
+            epoch: 1,
+            quorum_id: 0,
+            storage_root: ROOT,
+            index,
+        }
+    }
+
+    // writes raw values as `put_slice` does, skipping the columns given as `None`
+    fn put_raw(
+        storage: &Storage,
+        index: u64,
+        light_value: Option<&[u8]>,
+        value: Option<&[u8]>,
+        checksum: Option<&[u8]>,
+    ) {
+        let partition = storage.partitions.get_or_create(1).unwrap();
+        let index = slice_index(index);
+        let mut tx = partition.transaction();
+        if let Some(light_value) = light_value {
+            tx.put(PARTITION_COL_SLICE, &index.to_slice_key(), light_value);
+        }
+        if let Some(value) = value {
+            tx.put(PARTITION_COL_SLICE_DATA, &index.to_data_key(), value);
+        }
+        if let Some(checksum) = checksum {
+            tx.put(PARTITION_COL_CHECKSUM, &index.to_data_key(), checksum);
+        }
+        partition.write(tx).unwrap();
+    }
+
+    async fn check(storage: &Storage, index: usize) -> SliceCheck {
+        storage.check_slice(1, 0, ROOT, index).await.unwrap()
+    }
+
+    fn row_value() -> Vec<u8> {
+        let mut value = vec![];
+        vec![[3u8; 32]; 2]
+            .serialize_uncompressed(&mut value)
+            .unwrap();
+        value
+    }
+
+    #[tokio::test]
+    async fn check_slice_test() {
+        let storage = Storage::in_memory();
+        assert!(matches!(check(&storage, 0).await, SliceCheck::Missing));
+
+        let value = row_value();
+        put_raw(&storage, 0, Some(&[1, 2]), Some(&value), Some(&[0; 8]));
+        put_raw(&storage, 1, None, Some(&value), None);
+        put_raw(&storage, 2, Some(&[1, 2]), None, None);
+        assert!(matches!(
+            check(&storage, 0).await,
+            SliceCheck::Corrupted(e) if e == "checksum mismatch"
+        ));
+        assert!(matches!(
+            check(&storage, 1).await,
+            SliceCheck::Corrupted(e) if e == "light slice missing"
+        ));
+        assert!(matches!(
+            check(&storage, 2).await,
+            SliceCheck::Corrupted(e) if e == "row data missing"
+        ));
+        assert!(matches!(check(&storage, 3).await, SliceCheck::Missing));
+    }
+
+    #[tokio::test]
+    async fn check_legacy_slice_test() {
+        let storage = Storage::in_memory();
+        let value = row_value();
+        // slices without a checksum are only deserialized, which a truncated light slice fails
+        put_raw(&storage, 0, Some(&[1, 2]), Some(&value), None);
+        assert!(matches!(
+            check(&storage, 0).await,
+            SliceCheck::Corrupted(e) if e.starts_with("light slice:")
+        ));
+        // a matching checksum does not vouch for bytes written corrupted
+        let checksum = slice_checksum(&[1, 2], &value);
+        put_raw(&storage, 1, Some(&[1, 2]), Some(&value), Some(&checksum));
+        assert!(matches!(
+            check(&storage, 1).await,
+            SliceCheck::Corrupted(e) if e.starts_with("light slice:")
+        ));
+    }
+
+    async fn epoch_indicies(storage: &Storage) -> Vec<Vec<u16>> {
+        storage
+            .get_epoch_info(1)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|blob| blob.indicies)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn quarantine_slice_test() {
+        let storage = Storage::in_memory();
+        let value = row_value();
+        put_raw(&storage, 0, Some(&[1, 2]), Some(&value), Some(&[0; 8]));
+        put_raw(&storage, 1, Some(&[1, 2]), Some(&value), Some(&[0; 8]));
+        let partition = storage.partitions.get(1).unwrap();
+        let mut tx = partition.transaction();
+        tx.put(
+            PARTITION_COL_BLOB_INDEX,
+            &blob_key(1, 0, ROOT),
+            &bcs::to_bytes(&vec![0u16, 1]).unwrap(),
+        );
+        partition.write(tx).unwrap();
+        assert!(storage
+            .get_raw_slice(1, 0, ROOT, 0)
+            .await
+            .unwrap()
+            .is_some());
+
+        storage.quarantine_slice(1, 0, ROOT, 0).await.unwrap();
+        assert!(storage.get_slice(1, 0, ROOT, 0).await.unwrap().is_none());
+        assert!(storage
+            .get_raw_slice(1, 0, ROOT, 0)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(check(&storage, 0).await, SliceCheck::Missing));
+        // no longer listed with its blob, which is dropped with its last slice
+        assert_eq!(epoch_indicies(&storage).await, vec![vec![1]]);
+        storage.quarantine_slice(1, 0, ROOT, 1).await.unwrap();
+        assert!(epoch_indicies(&storage).await.is_empty());
+
+        // the bytes are kept until the epoch is pruned
+        let quarantined = partition
+            .get(PARTITION_COL_QUARANTINE, &slice_index(0).to_data_key())
+            .unwrap()
+            .unwrap();
+        let values: [Option<Vec<u8>>; 3] = bcs::from_bytes(&quarantined).unwrap();
+        assert_eq!(values, [Some(vec![1, 2]), Some(value), Some(vec![0; 8])]);
+    }
+}
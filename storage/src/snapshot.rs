@@ -0,0 +1,264 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Result};
+use kvdb::KeyValueDB;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    backend::MAIN_DATABASE,
+    misc_db::MiscDB,
+    partition::{NamedPartitions, PARTITION_COL_NUM},
+    schema::get_schema_version,
+    RocksDbBackend, Storage, StorageBackend, StorageConfig, COL_NUM, SCHEMA_VERSION,
+};
+
+/// File describing a snapshot, at the root of its directory.
+pub const SNAPSHOT_MANIFEST: &str = "snapshot_manifest.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    // unix timestamp in seconds
+    pub created_at: u64,
+    pub schema_version: u64,
+    // next block to sync DA events from
+    pub sync_progress: Option<u64>,
+    // last pruned epoch
+    pub prune_progress: Option<u64>,
+    // first and last epoch of the slice partitions
+    pub epochs: Option<(u64, u64)>,
+    // number of keys in each column of the main database and of each slice partition
+    pub keys: BTreeMap<String, Vec<u64>>,
+}
+
+fn count_keys(db: &dyn KeyValueDB, columns: u32) -> Result<Vec<u64>> {
+    let mut keys = vec![];
+    for col in 0..columns {
+        let mut count = 0;
+        for item in db.iter(col) {
+            item?;
+            count += 1;
+        }
+        keys.push(count);
+    }
+    Ok(keys)
+}
+
+/// Number of keys in each column of the main database and of each slice partition.
+fn database_keys(backend: &dyn StorageBackend) -> Result<BTreeMap<String, Vec<u64>>> {
+    let mut keys = BTreeMap::from([(
+        MAIN_DATABASE.to_string(),
+        count_keys(&*backend.main(), COL_NUM)?,
+    )]);
+    for name in backend.partitions()? {
+        let partition = backend.open_partition(&name)?;
+        keys.insert(name, count_keys(&*partition, PARTITION_COL_NUM)?);
+    }
+    Ok(keys)
+}
+
+/// Checks that the databases of a snapshot hold what its manifest records.
+fn validate_snapshot(manifest: &SnapshotManifest, backend: &dyn StorageBackend) -> Result<()> {
+    if get_schema_version(&*backend.main())? != Some(manifest.schema_version) {
+        bail!("snapshot schema version mismatches its manifest");
+    }
+    if database_keys(backend)? != manifest.keys {
+        bail!("snapshot databases mismatch the key counts of its manifest");
+    }
+    Ok(())
+}
+
+/// Checkpoints the databases into `dir`, holding the partition handles until done so that
+/// partitions pruned meanwhile stay on disk, and counts the keys of the checkpoint.
+fn checkpoint(
+    backend: &dyn StorageBackend,
+    dir: &Path,
+    partitions: NamedPartitions,
+) -> Result<BTreeMap<String, Vec<u64>>> {
+    let names: Vec<String> = partitions.iter().map(|(name, _)| name.clone()).collect();
+    backend.checkpoint(dir, &names)?;
+    drop(partitions);
+    database_keys(&RocksDbBackend::open(dir, &StorageConfig::default())?)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+impl Storage {
+    /// Takes a snapshot of the running node into `dir`, which can be restored as a data path.
+    ///
+    /// Each database is checkpointed by RocksDB while writes go on, and the progress is recorded
+    /// before, so a node restored from the snapshot syncs and prunes again whatever was written
+    /// meanwhile.
+    pub async fn snapshot(&self, dir: impl AsRef<Path>) -> Result<SnapshotManifest> {
+        let dir = dir.as_ref();
+        if dir.exists() {
+            bail!("snapshot directory {:?} already exists", dir);
+        }
+        // renamed once complete, so that interrupted snapshots are never restored
+        let partial = dir.with_extension("partial");
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        let sync_progress = self.get_sync_progress().await?;
+        let prune_progress = self.get_prune_progress().await?;
+        let schema_version =
+            get_schema_version(&*self.db)?.ok_or_else(|| anyhow!("schema version missing"))?;
+        let epochs = self.partitions.epoch_range();
+
+        // the handles keep pruned partitions on disk until they are checkpointed
+        let partitions = self.partitions.snapshot();
+        let backend = self.partitions.backend().clone();
+        let checkpoint_dir = partial.clone();
+        let keys =
+            tokio::task::spawn_blocking(move || checkpoint(&*backend, &checkpoint_dir, partitions))
+                .await??;
+        let manifest = SnapshotManifest {
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            schema_version,
+            sync_progress,
+            prune_progress,
+            epochs,
+            keys,
+        };
+        fs::write(
+            partial.join(SNAPSHOT_MANIFEST),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        fs::rename(&partial, dir)?;
+        info!(
+            "database snapshot taken at {:?} with epochs {:?}",
+            dir, manifest.epochs
+        );
+        Ok(manifest)
+    }
+
+    /// Validates a snapshot against its manifest and copies it to `data_path`, which must be
+    /// empty or missing.
+    pub fn restore(
+        snapshot: impl AsRef<Path>,
+        data_path: impl AsRef<Path>,
+    ) -> Result<SnapshotManifest> {
+        let (snapshot, data_path) = (snapshot.as_ref(), data_path.as_ref());
+        let manifest: SnapshotManifest =
+            serde_json::from_slice(&fs::read(snapshot.join(SNAPSHOT_MANIFEST))?)?;
+        if manifest.schema_version > SCHEMA_VERSION {
+            bail!(
+                "snapshot schema version {} is newer than the supported version {}",
+                manifest.schema_version,
+                SCHEMA_VERSION
+            );
+        }
+        if data_path.exists() && fs::read_dir(data_path)?.next().is_some() {
+            bail!("data path {:?} is not empty", data_path);
+        }
+        copy_dir(snapshot, data_path)?;
+        // validated on the copy, since opening the databases writes to them
+        let validated = RocksDbBackend::open(data_path, &StorageConfig::default())
+            .and_then(|backend| validate_snapshot(&manifest, &backend));
+        if let Err(e) = validated {
+            fs::remove_dir_all(data_path)?;
+            return Err(e);
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition::PARTITION_COL_SLICE_DATA;
+    use std::{env, process};
+
+    #[tokio::test]
+    async fn snapshot_test() {
+        let dir = env::temp_dir().join(format!("zg-da-node-snapshot-{}", process::id()));
+        let storage = Storage::new(dir.join("data"), &StorageConfig::default()).unwrap();
+        storage.put_sync_progress(5).await.unwrap();
+        storage.put_prune_progress(2).await.unwrap();
+        let partition = storage.partitions.get_or_create(3).unwrap();
+        let mut tx = partition.transaction();
+        tx.put(PARTITION_COL_SLICE_DATA, &[1], &[2]);
+        partition.write(tx).unwrap();
+
+        let manifest = storage.snapshot(dir.join("snapshot")).await.unwrap();
+        assert_eq!(manifest.sync_progress, Some(5));
+        assert_eq!(manifest.prune_progress, Some(2));
+        assert_eq!(manifest.schema_version, SCHEMA_VERSION);
+        assert_eq!(manifest.epochs, Some((3, 3)));
+        assert_eq!(manifest.keys["3-3"], vec![0, 0, 1, 0, 0]);
+        assert!(storage.snapshot(dir.join("snapshot")).await.is_err());
+
+        let restored = Storage::restore(dir.join("snapshot"), dir.join("restored")).unwrap();
+        assert_eq!(restored, manifest);
+        let restored = Storage::new(dir.join("restored"), &StorageConfig::default()).unwrap();
+        assert_eq!(restored.get_sync_progress().await.unwrap(), Some(5));
+        assert_eq!(
+            restored
+                .partitions
+                .get(3)
+                .unwrap()
+                .get(PARTITION_COL_SLICE_DATA, &[1])
+                .unwrap(),
+            Some(vec![2])
+        );
+        // data paths in use are refused
+        assert!(Storage::restore(dir.join("snapshot"), dir.join("restored")).is_err());
+
+        // copies altered afterwards are refused and removed
+        let snapshot =
+            RocksDbBackend::open(dir.join("snapshot"), &StorageConfig::default()).unwrap();
+        let mut tx = snapshot.main().transaction();
+        tx.put(0, &[9], &[9]);
+        snapshot.main().write(tx).unwrap();
+        drop(snapshot);
+        assert!(Storage::restore(dir.join("snapshot"), dir.join("altered")).is_err());
+        assert!(!dir.join("altered").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshot_pruned_partition_test() {
+        let dir = env::temp_dir().join(format!("zg-da-node-snapshot-pruned-{}", process::id()));
+        let storage = Storage::new(dir.join("data"), &StorageConfig::default()).unwrap();
+        let partition = storage.partitions.get_or_create(3).unwrap();
+        let mut tx = partition.transaction();
+        tx.put(PARTITION_COL_SLICE_DATA, &[1], &[2]);
+        partition.write(tx).unwrap();
+        drop(partition);
+
+        // pruned after the partitions are listed and before they are checkpointed
+        let partitions = storage.partitions.snapshot();
+        storage.partitions.prune(3);
+        let keys = checkpoint(
+            &**storage.partitions.backend(),
+            &dir.join("snapshot"),
+            partitions,
+        )
+        .unwrap();
+        assert_eq!(keys["3-3"], vec![0, 0, 1, 0, 0]);
+        // removed once its last handle drops
+        assert!(storage
+            .partitions
+            .backend()
+            .partitions()
+            .unwrap()
+            .is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
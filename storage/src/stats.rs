@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use kvdb::KeyValueDB;
+
+use crate::{
+    backend::MAIN_DATABASE, partition::PARTITION_COL_NUM, slice_db::SliceDB, Storage, COL_NUM,
+};
+
+// names of the main database columns, by index
+pub(crate) const COLUMN_NAMES: [&str; COL_NUM as usize] = [
+    "misc",
+    "slice (legacy)",
+    "quorum",
+    "quorum_num",
+    "blob_status",
+    "erasure_commitment",
+    "registration",
+    "transaction",
+    "blob_index (legacy)",
+    "slice_data (legacy)",
+];
+// names of the slice partition columns, by index
+pub(crate) const PARTITION_COLUMN_NAMES: [&str; PARTITION_COL_NUM as usize] = [
+    "blob_index",
+    "slice",
+    "slice_data",
+    "checksum",
+    "quarantine",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnStats {
+    // "main" or the name of a slice partition
+    pub database: String,
+    pub column: &'static str,
+    pub keys: u64,
+    // total size of the keys and values
+    pub bytes: u64,
+}
+
+/// RocksDB statistics of one database, see `StorageBackend::database_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DatabaseStats {
+    // "main" or the name of a slice partition
+    pub database: String,
+    // size of the files of the database on disk
+    pub size_bytes: u64,
+    // estimated number of keys by column name
+    pub column_keys: Vec<(&'static str, u64)>,
+    // size of the SST files by column name
+    pub column_bytes: Vec<(&'static str, u64)>,
+    // counted since the database was opened
+    pub block_cache_hits: u64,
+    pub block_cache_misses: u64,
+    pub write_stall_micros: u64,
+    // estimated bytes left to compaction, a backlog that grows before writes stall
+    pub pending_compaction_bytes: u64,
+}
+
+/// Blobs, slices and bytes stored in one epoch, see `Storage::epoch_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochStats {
+    pub epoch: u64,
+    pub blobs: u64,
+    pub slices: u64,
+    // SST files of the epoch's partition, shared by the slices of each epoch if it holds several
+    pub bytes: u64,
+}
+
+fn scan_database(
+    database: &str,
+    db: &dyn KeyValueDB,
+    column_names: &[&'static str],
+) -> Result<Vec<ColumnStats>> {
+    let mut stats = vec![];
+    for (col, &column) in column_names.iter().enumerate() {
+        let (mut keys, mut bytes) = (0, 0);
+        for item in db.iter(col as u32) {
+            let (key, value) = item?;
+            keys += 1;
+            bytes += (key.len() + value.len()) as u64;
+        }
+        stats.push(ColumnStats {
+            database: database.to_string(),
+            column,
+            keys,
+            bytes,
+        });
+    }
+    Ok(stats)
+}
+
+impl Storage {
+    /// Key counts and sizes of every column, read by a full scan.
+    pub fn column_stats(&self) -> Result<Vec<ColumnStats>> {
+        let mut stats = scan_database(MAIN_DATABASE, &*self.db, &COLUMN_NAMES)?;
+        let partitions = self.partitions.snapshot();
+        for (name, partition) in partitions {
+            stats.extend(scan_database(&name, &*partition, &PARTITION_COLUMN_NAMES)?);
+        }
+        Ok(stats)
+    }
+
+    /// RocksDB statistics of the main database and the slice partitions, cheap to read.
+    pub fn database_stats(&self) -> Result<Vec<DatabaseStats>> {
+        self.partitions.backend().database_stats()
+    }
+
+    /// Statistics of the epochs with blobs stored, read from the blob indices.
+    pub async fn epoch_stats(&self) -> Result<Vec<EpochStats>> {
+        let partition_bytes: HashMap<_, u64> = self
+            .database_stats()?
+            .into_iter()
+            .map(|stats| {
+                (
+                    stats.database,
+                    stats.column_bytes.iter().map(|(_, x)| x).sum(),
+                )
+            })
+            .collect();
+        let mut stats = vec![];
+        for (name, epochs) in self.partitions.epoch_ranges() {
+            let first = stats.len();
+            for epoch in epochs {
+                let blobs = self.get_epoch_info(epoch).await?;
+                if blobs.is_empty() {
+                    continue;
+                }
+                stats.push(EpochStats {
+                    epoch,
+                    blobs: blobs.len() as u64,
+                    slices: blobs.iter().map(|blob| blob.indicies.len() as u64).sum(),
+                    bytes: 0,
+                });
+            }
+            let slices: u64 = stats[first..].iter().map(|epoch| epoch.slices).sum();
+            let bytes = partition_bytes.get(&name).copied().unwrap_or_default();
+            // shared evenly by epochs whose blobs have no slices
+            let epochs = (stats.len() - first) as u64;
+            for epoch in &mut stats[first..] {
+                epoch.bytes = match slices {
+                    0 => bytes / epochs,
+                    _ => (bytes as u128 * epoch.slices as u128 / slices as u128) as u64,
+                };
+            }
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blob_status_db::{BlobStatus, BlobStatusDB},
+        partition::PARTITION_COL_BLOB_INDEX,
+        StorageConfig,
+    };
+    use std::{env, fs, process};
+
+    #[tokio::test]
+    async fn column_stats_test() {
+        let storage = Storage::in_memory();
+        storage
+            .put_blob(1, 0, [0; 32], BlobStatus::UPLOADED)
+            .await
+            .unwrap();
+        storage.partitions.get_or_create(1).unwrap();
+
+        let stats = storage.column_stats().unwrap();
+        assert_eq!(stats.len(), (COL_NUM + PARTITION_COL_NUM) as usize);
+        let blob_status = stats.iter().find(|s| s.column == "blob_status").unwrap();
+        assert_eq!((blob_status.keys, blob_status.bytes), (1, 48 + 8));
+        assert!(stats
+            .iter()
+            .any(|s| s.database == "1-1" && s.column == "quarantine"));
+    }
+
+    #[tokio::test]
+    async fn database_stats_test() {
+        assert!(Storage::in_memory().database_stats().unwrap().is_empty());
+
+        let path = env::temp_dir().join(format!("zg-da-node-stats-{}", process::id()));
+        let storage = Storage::new(&path, &StorageConfig::default()).unwrap();
+        storage
+            .put_blob(1, 0, [0; 32], BlobStatus::UPLOADED)
+            .await
+            .unwrap();
+        storage.put_slice(1, 0, [0; 32], vec![]).await.unwrap();
+
+        let stats = storage.database_stats().unwrap();
+        let databases: Vec<_> = stats.iter().map(|s| s.database.as_str()).collect();
+        assert_eq!(databases, vec!["main", "1-1"]);
+        let main = &stats[0];
+        assert!(main.size_bytes > 0);
+        assert_eq!(main.column_keys.len(), COL_NUM as usize);
+        assert_eq!(main.column_keys[4], ("blob_status", 1));
+        assert_eq!(main.column_bytes[4], ("blob_status", 48 + 8));
+        assert_eq!(main.pending_compaction_bytes, 0);
+        assert_eq!(stats[1].column_keys[0], ("blob_index", 1));
+        assert!(stats[1].column_bytes[0].1 > 0);
+        drop(storage);
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn epoch_stats_test() {
+        let path = env::temp_dir().join(format!("zg-da-node-epoch-stats-{}", process::id()));
+        let config = StorageConfig {
+            epochs_per_partition: Some(4),
+            ..Default::default()
+        };
+        let storage = Storage::new(&path, &config).unwrap();
+        // blob indices of 3 slices in epoch 1 and 1 slice in epoch 2, in the partition "0-3"
+        let partition = storage.partitions.get_or_create(1).unwrap();
+        let mut tx = partition.transaction();
+        for (epoch, indices) in [(1u64, vec![0u16, 1, 2]), (2, vec![5])] {
+            let key: Vec<u8> = epoch
+                .to_be_bytes()
+                .into_iter()
+                .chain(0u64.to_be_bytes())
+                .chain([0; 32])
+                .collect();
+            tx.put(
+                PARTITION_COL_BLOB_INDEX,
+                &key,
+                &bcs::to_bytes(&indices).unwrap(),
+            );
+        }
+        partition.write(tx).unwrap();
+
+        let stats = storage.epoch_stats().await.unwrap();
+        let bytes: u64 = storage.database_stats().unwrap()[1]
+            .column_bytes
+            .iter()
+            .map(|(_, x)| x)
+            .sum();
+        assert_eq!(
+            stats,
+            vec![
+                EpochStats {
+                    epoch: 1,
+                    blobs: 1,
+                    slices: 3,
+                    bytes: bytes * 3 / 4,
+                },
+                EpochStats {
+                    epoch: 2,
+                    blobs: 1,
+                    slices: 1,
+                    bytes: bytes / 4,
+                },
+            ]
+        );
+        drop((partition, storage));
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::COL_TRANSACTION;
+
+use super::Storage;
+use anyhow::Result;
+use async_trait::async_trait;
+use kvdb::KeyValueDB;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InFlightTransaction {
+    pub sender: [u8; 20],
+    pub nonce: u64,
+    // json encoded description of what the transaction does
+    pub tx_info: Vec<u8>,
+    // json encoded typed transaction carrying the latest fees
+    pub tx: Vec<u8>,
+    // hashes of all broadcast attempts with this nonce, any of them may be mined
+    pub tx_hashes: Vec<[u8; 32]>,
+}
+
+#[async_trait]
+pub trait TransactionDB {
+    async fn put_in_flight_transaction(&self, record: InFlightTransaction) -> Result<()>;
+
+    async fn delete_in_flight_transaction(&self, sender: [u8; 20], nonce: u64) -> Result<()>;
+
+    async fn get_in_flight_transactions(
+        &self,
+        sender: [u8; 20],
+    ) -> Result<Vec<InFlightTransaction>>;
+}
+
+fn get_transaction_key(sender: [u8; 20], nonce: u64) -> Vec<u8> {
+    sender.into_iter().chain(nonce.to_be_bytes()).collect()
+}
+
+#[async_trait]
+impl TransactionDB for Storage {
+    async fn put_in_flight_transaction(&self, record: InFlightTransaction) -> Result<()> {
+        let mut tx = self.db.transaction();
+        tx.put(
+            COL_TRANSACTION,
+            &get_transaction_key(record.sender, record.nonce),
+            &bincode::serialize(&record)?,
+        );
+        self.db.write(tx)?;
+        Ok(())
+    }
+
+    async fn delete_in_flight_transaction(&self, sender: [u8; 20], nonce: u64) -> Result<()> {
+        let mut tx = self.db.transaction();
+        tx.delete(COL_TRANSACTION, &get_transaction_key(sender, nonce));
+        self.db.write(tx)?;
+        Ok(())
+    }
+
+    async fn get_in_flight_transactions(
+        &self,
+        sender: [u8; 20],
+    ) -> Result<Vec<InFlightTransaction>> {
+        let mut records = vec![];
+        for item in KeyValueDB::iter_with_prefix(&*self.db, COL_TRANSACTION, &sender) {
+            let (_, value) = item?;
+            records.push(bincode::deserialize(&value)?);
+        }
+        Ok(records)
+    }
+}
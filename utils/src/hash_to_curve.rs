@@ -0,0 +1,191 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use ark_bn254::{Fq, G1Affine};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, Field, One, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+
+use crate::map_to_g1;
+
+/// Domain separation tag of the RFC 9380 scheme.
+pub const HASH_TO_G1_DST: &[u8] = b"0G_DA_BN254G1_XMD:SHA-256_SVDW_RO_";
+
+// SHA-256 block and output sizes in bytes
+const SHA256_BLOCK_SIZE: usize = 64;
+const SHA256_OUTPUT_SIZE: usize = 32;
+// ceil((ceil(log2(p)) + 128) / 8) bytes per field element
+const FIELD_ELEMENT_BYTES: usize = 48;
+
+/// How messages are hashed to G1, which must be the same as the DA contracts of the deployment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum G1HashScheme {
+    /// Try-and-increment on the x coordinate, as in `map_to_g1`.
+    #[default]
+    TryAndIncrement,
+    /// RFC 9380 `hash_to_curve` with `expand_message_xmd` (SHA-256) and the SVDW map.
+    Rfc9380Svdw,
+}
+
+impl G1HashScheme {
+    /// Hashes the keccak256 digest of a message to G1.
+    pub fn hash(&self, digest: &[u8]) -> G1Affine {
+        match self {
+            G1HashScheme::TryAndIncrement => map_to_g1(digest.to_vec()),
+            G1HashScheme::Rfc9380Svdw => hash_to_curve_svdw(digest, HASH_TO_G1_DST),
+        }
+    }
+}
+
+impl FromStr for G1HashScheme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "try_and_increment" => Ok(G1HashScheme::TryAndIncrement),
+            "rfc9380" => Ok(G1HashScheme::Rfc9380Svdw),
+            _ => bail!("unknown hash to G1 scheme `{}`", s),
+        }
+    }
+}
+
+/// `expand_message_xmd` of RFC 9380 section 5.3.1 with SHA-256.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len_in_bytes: usize) -> Result<Vec<u8>> {
+    let ell = len_in_bytes.div_ceil(SHA256_OUTPUT_SIZE);
+    if ell > 255 || len_in_bytes > 65535 || dst.len() > 255 {
+        bail!("invalid expand_message_xmd parameters");
+    }
+    let mut dst_prime = dst.to_vec();
+    dst_prime.push(dst.len() as u8);
+
+    let b_0 = Sha256::new()
+        .chain_update([0u8; SHA256_BLOCK_SIZE])
+        .chain_update(msg)
+        .chain_update((len_in_bytes as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+    let mut uniform_bytes = b_i.to_vec();
+    for i in 2..=ell {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+    uniform_bytes.truncate(len_in_bytes);
+    Ok(uniform_bytes)
+}
+
+/// `hash_to_field` of RFC 9380 section 5.2 into the base field.
+pub fn hash_to_field(msg: &[u8], dst: &[u8], count: usize) -> Vec<Fq> {
+    expand_message_xmd(msg, dst, count * FIELD_ELEMENT_BYTES)
+        .expect("parameters are fixed")
+        .chunks(FIELD_ELEMENT_BYTES)
+        .map(Fq::from_be_bytes_mod_order)
+        .collect()
+}
+
+fn sgn0(x: Fq) -> bool {
+    x.into_bigint().is_odd()
+}
+
+fn g(x: Fq) -> Fq {
+    x * x * x + Fq::from(3)
+}
+
+/// The Shallue-van de Woestijne map of RFC 9380 section 6.6.1 for y^2 = x^3 + 3, with Z = 1.
+pub fn map_to_curve_svdw(u: Fq) -> G1Affine {
+    let z = Fq::one();
+    let c1 = g(z);
+    let c2 = -z / Fq::from(2);
+    let mut c3 = (-g(z) * Fq::from(3) * z * z)
+        .sqrt()
+        .expect("-g(Z) * 3Z^2 is a square");
+    if sgn0(c3) {
+        c3 = -c3;
+    }
+    let c4 = -Fq::from(4) * g(z) / (Fq::from(3) * z * z);
+
+    let tv1 = u * u * c1;
+    let tv2 = Fq::one() + tv1;
+    let tv1 = Fq::one() - tv1;
+    let tv3 = (tv1 * tv2).inverse().unwrap_or(Fq::zero());
+    let tv4 = u * tv1 * tv3 * c3;
+    let x1 = c2 - tv4;
+    let x2 = c2 + tv4;
+    let x3 = (tv2 * tv2 * tv3).square() * c4 + z;
+    let x = if g(x1).legendre().is_qr() {
+        x1
+    } else if g(x2).legendre().is_qr() {
+        x2
+    } else {
+        x3
+    };
+    let mut y = g(x).sqrt().expect("one of the candidates is on the curve");
+    if sgn0(u) != sgn0(y) {
+        y = -y;
+    }
+    G1Affine::new(x, y)
+}
+
+/// `hash_to_curve` of RFC 9380 section 3 into G1, whose cofactor is 1.
+pub fn hash_to_curve_svdw(msg: &[u8], dst: &[u8]) -> G1Affine {
+    let u = hash_to_field(msg, dst, 2);
+    (map_to_curve_svdw(u[0]) + map_to_curve_svdw(u[1])).into_affine()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_message_xmd_test() {
+        // RFC 9380 appendix K.1
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        assert_eq!(
+            ethers::utils::hex::encode(expand_message_xmd(b"", dst, 0x20).unwrap()),
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235"
+        );
+        assert_eq!(
+            ethers::utils::hex::encode(expand_message_xmd(b"abc", dst, 0x20).unwrap()),
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615"
+        );
+    }
+
+    #[test]
+    fn hash_to_curve_svdw_test() {
+        // BN254G1_XMD:SHA-256_SVDW_RO_ vectors of gnark-crypto
+        let dst = b"QUUX-V01-CS02-with-BN254G1_XMD:SHA-256_SVDW_RO_";
+        let vectors = [
+            (
+                &b""[..],
+                "0a976ab906170db1f9638d376514dbf8c42aef256a54bbd48521f20749e59e86",
+                "02925ead66b9e68bfc309b014398640ab55f6619ab59bc1fab2210ad4c4d53d5",
+            ),
+            (
+                &b"abc"[..],
+                "23f717bee89b1003957139f193e6be7da1df5f1374b26a4643b0378b5baf53d1",
+                "04142f826b71ee574452dbc47e05bc3e1a647478403a7ba38b7b93948f4e151d",
+            ),
+        ];
+        for (msg, x, y) in vectors {
+            let point = hash_to_curve_svdw(msg, dst);
+            assert_eq!(
+                ethers::utils::hex::encode(point.x.into_bigint().to_bytes_be()),
+                x
+            );
+            assert_eq!(
+                ethers::utils::hex::encode(point.y.into_bigint().to_bytes_be()),
+                y
+            );
+        }
+    }
+}
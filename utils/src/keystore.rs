@@ -0,0 +1,107 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use ethers::types::H256;
+
+/// Environment variable holding the keystore password if no password file is configured.
+pub const KEYSTORE_PASSWORD_ENV: &str = "ZG_DA_KEYSTORE_PASSWORD";
+
+pub fn read_keystore_password(password_file: Option<&str>) -> Result<String> {
+    match password_file {
+        Some(path) => Ok(fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read keystore password file {}: {:?}", path, e))?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()),
+        None => env::var(KEYSTORE_PASSWORD_ENV).map_err(|_| {
+            anyhow!(
+                "Keystore password missing, set {} or a password file",
+                KEYSTORE_PASSWORD_ENV
+            )
+        }),
+    }
+}
+
+/// Decrypts an Ethereum JSON keystore (v3).
+pub fn decrypt_eth_key(path: impl AsRef<Path>, password: &str) -> Result<H256> {
+    let key = decrypt(path.as_ref(), password)?;
+    if key.len() != 32 {
+        bail!(anyhow!("Invalid eth private key length: {}", key.len()));
+    }
+    Ok(H256::from_slice(&key))
+}
+
+/// Decrypts a BLS keystore, which is a JSON keystore (v3) holding the big-endian scalar.
+pub fn decrypt_bls_key(path: impl AsRef<Path>, password: &str) -> Result<Fr> {
+    let key = decrypt(path.as_ref(), password)?;
+    let scalar = Fr::from_be_bytes_mod_order(&key);
+    if scalar.into_bigint().to_bytes_be() != key {
+        bail!(anyhow!("Invalid bls private key in keystore"));
+    }
+    Ok(scalar)
+}
+
+pub fn encrypt_eth_key(dir: impl AsRef<Path>, name: &str, key: H256, password: &str) -> Result<()> {
+    encrypt(dir.as_ref(), name, key.as_bytes(), password)
+}
+
+pub fn encrypt_bls_key(dir: impl AsRef<Path>, name: &str, key: Fr, password: &str) -> Result<()> {
+    encrypt(
+        dir.as_ref(),
+        name,
+        &key.into_bigint().to_bytes_be(),
+        password,
+    )
+}
+
+fn decrypt(path: &Path, password: &str) -> Result<Vec<u8>> {
+    eth_keystore::decrypt_key(path, password)
+        .map_err(|e| anyhow!("Cannot decrypt keystore {:?}: {:?}", path, e))
+}
+
+fn encrypt(dir: &Path, name: &str, key: &[u8], password: &str) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    eth_keystore::encrypt_key(dir, &mut rand::thread_rng(), key, password, Some(name))
+        .map_err(|e| anyhow!("Cannot write keystore {:?}: {:?}", dir.join(name), e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn keystore_dir(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("zg-da-node-keystore-{}-{}", name, process::id()))
+    }
+
+    #[test]
+    fn eth_keystore_test() {
+        let dir = keystore_dir("eth");
+        let key = H256::repeat_byte(7);
+        encrypt_eth_key(&dir, "eth.json", key, "password").unwrap();
+        assert_eq!(
+            decrypt_eth_key(dir.join("eth.json"), "password").unwrap(),
+            key
+        );
+        assert!(decrypt_eth_key(dir.join("eth.json"), "wrong").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bls_keystore_test() {
+        let dir = keystore_dir("bls");
+        let key = Fr::from(123456789u64);
+        encrypt_bls_key(&dir, "bls.json", key, "password").unwrap();
+        assert_eq!(
+            decrypt_bls_key(dir.join("bls.json"), "password").unwrap(),
+            key
+        );
+        assert!(decrypt_bls_key(dir.join("bls.json"), "wrong").is_err());
+        // an eth key above the field modulus is not a bls key
+        encrypt_eth_key(&dir, "eth.json", H256::repeat_byte(0xff), "password").unwrap();
+        assert!(decrypt_bls_key(dir.join("eth.json"), "password").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
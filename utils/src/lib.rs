@@ -1,8 +1,17 @@
+pub mod hash_to_curve;
+pub mod keystore;
+pub mod line;
 pub mod metrics;
 
-use ark_bn254::{Fq, FqConfig, G1Affine};
+use ark_bn254::{Fq, FqConfig, G1Affine, G1Projective};
 
-use ark_ff::{BigInt, Field, MontConfig, One};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInt, BigInteger, Field, MontConfig, One, PrimeField};
+use ethers::{
+    abi::{self, Token},
+    utils::keccak256,
+};
+use hash_to_curve::G1HashScheme;
 
 pub fn hex_to_bytes(s: &str) -> Option<Vec<u8>> {
     if s.len() % 2 == 0 {
@@ -31,6 +40,28 @@ pub fn map_to_g1(digest: Vec<u8>) -> G1Affine {
     }
 }
 
+/// The message signed for a blob, hashed to G1 the same way as the DA contract.
+pub fn blob_verified_hash(
+    data_root: [u8; 32],
+    epoch: u64,
+    quorum_id: u64,
+    erasure_commitment: G1Projective,
+    hash_scheme: G1HashScheme,
+) -> G1Affine {
+    let point = erasure_commitment.into_affine();
+    let hash = keccak256(
+        abi::encode_packed(&[
+            Token::FixedBytes(data_root.to_vec()),
+            Token::FixedBytes(left_pad_zeros(epoch, 32)),
+            Token::FixedBytes(left_pad_zeros(quorum_id, 32)),
+            Token::FixedBytes(point.x.into_bigint().to_bytes_be()),
+            Token::FixedBytes(point.y.into_bigint().to_bytes_be()),
+        ])
+        .unwrap(),
+    );
+    hash_scheme.hash(&hash)
+}
+
 pub fn left_pad_zeros(x: u64, l: usize) -> Vec<u8> {
     let mut res = vec![0; l - 8];
     res.append(&mut x.to_be_bytes().to_vec());
@@ -0,0 +1,125 @@
+use std::collections::VecDeque;
+
+use tiny_keccak::{Hasher, Keccak};
+use zg_encoder::constants::{BLOB_COL_N, BLOB_UNIT};
+
+pub const NUM_SUBLINES: usize = 32;
+pub const LINE_BYTES: usize = BLOB_COL_N * BLOB_UNIT;
+pub const SUBLINE_BYTES: usize = LINE_BYTES / NUM_SUBLINES;
+
+pub fn build_subline_merkle(data: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+    assert_eq!(data.len() * 32, LINE_BYTES);
+
+    let flow_leaves = keccak_chunked(data, 256 / 32);
+
+    let mut last_layer = flow_leaves;
+    while last_layer.len() > NUM_SUBLINES {
+        last_layer = keccak_chunked(&last_layer, 2);
+    }
+
+    let mut tree = VecDeque::new();
+
+    while last_layer.len() > 1 {
+        let next_layer = keccak_chunked(&last_layer, 2);
+        let mut to_push_layer = next_layer;
+        // last_layer is to_push_layer
+        std::mem::swap(&mut last_layer, &mut to_push_layer);
+        tree.push_front(to_push_layer);
+    }
+
+    tree.into()
+}
+
+/// Checks a row against the merkle proof of its slice, as completed by sample responses: the
+/// line root is folded with the proof by the bits of the line index, and the remaining bits
+/// select one of the blob roots.
+pub fn verify_line(
+    line: &[[u8; 32]],
+    line_index: usize,
+    merkle_proof: &[[u8; 32]],
+    blob_roots: &[[u8; 32]],
+) -> bool {
+    if line.len() * 32 != LINE_BYTES {
+        return false;
+    }
+    let mut node = keccak_chunked(&build_subline_merkle(line)[0], 2)[0];
+    let mut position = line_index;
+    for sibling in merkle_proof {
+        node = if position & 1 == 0 {
+            keccak_chunked(&[node, *sibling], 2)[0]
+        } else {
+            keccak_chunked(&[*sibling, node], 2)[0]
+        };
+        position /= 2;
+    }
+    blob_roots.get(position) == Some(&node)
+}
+
+pub fn keccak_chunked(input: &[[u8; 32]], chunk_size: usize) -> Vec<[u8; 32]> {
+    input
+        .chunks_exact(chunk_size)
+        .map(|x| {
+            let mut result = [0u8; 32];
+            let mut keccak256 = Keccak::v256();
+            for s in x {
+                keccak256.update(s.as_ref());
+            }
+            keccak256.finalize(&mut result);
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_line_test() {
+        let lines: Vec<Vec<[u8; 32]>> = (0..4u8).map(|i| vec![[i; 32]; LINE_BYTES / 32]).collect();
+        let roots: Vec<[u8; 32]> = lines
+            .iter()
+            .map(|line| keccak_chunked(&build_subline_merkle(line)[0], 2)[0])
+            .collect();
+        let parents = keccak_chunked(&roots, 2);
+        let blob_roots = [keccak_chunked(&parents, 2)[0], [0; 32], [0; 32]];
+
+        assert!(verify_line(
+            &lines[2],
+            2,
+            &[roots[3], parents[0]],
+            &blob_roots
+        ));
+        assert!(verify_line(
+            &lines[1],
+            1,
+            &[roots[0], parents[1]],
+            &blob_roots
+        ));
+        // wrong row, position or root
+        assert!(!verify_line(
+            &lines[3],
+            2,
+            &[roots[3], parents[0]],
+            &blob_roots
+        ));
+        assert!(!verify_line(
+            &lines[2],
+            3,
+            &[roots[3], parents[0]],
+            &blob_roots
+        ));
+        assert!(!verify_line(
+            &lines[2],
+            6,
+            &[roots[3], parents[0]],
+            &blob_roots
+        ));
+        assert!(!verify_line(
+            &lines[2][1..],
+            2,
+            &[roots[3], parents[0]],
+            &blob_roots
+        ));
+    }
+}
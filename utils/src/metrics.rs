@@ -1,7 +1,8 @@
 use lazy_static::lazy_static;
 use prometheus::{
-    opts, register_counter_vec, register_gauge, register_histogram_vec, register_int_gauge,
-    CounterVec, Gauge, HistogramVec, IntGauge,
+    opts, register_counter_vec, register_gauge, register_gauge_vec, register_histogram_vec,
+    register_int_counter, register_int_gauge, register_int_gauge_vec, CounterVec, Gauge, GaugeVec,
+    HistogramVec, IntCounter, IntGauge, IntGaugeVec,
 };
 
 lazy_static! {
@@ -25,8 +26,128 @@ lazy_static! {
         register_gauge!(opts!("sync_progress", "The chain log sync progress.",)).unwrap();
     pub static ref EPOCH_QUORUMS: IntGauge =
         register_int_gauge!(opts!("quorums", "The quorums for latest epoch.",)).unwrap();
+    pub static ref QUORUM_READY_EPOCH: IntGauge = register_int_gauge!(opts!(
+        "quorum_ready_epoch",
+        "The latest epoch whose quorums are cached locally.",
+    ))
+    .unwrap();
+    pub static ref QUORUM_CACHE_READY: IntGauge = register_int_gauge!(opts!(
+        "quorum_cache_ready",
+        "Whether quorums of the current epoch are cached locally.",
+    ))
+    .unwrap();
     pub static ref REGISTERED_EPOCH: Gauge =
         register_gauge!(opts!("epoch", "The latest registered epoch.",)).unwrap();
+    pub static ref REGISTRATION_DEADLINE_BLOCKS: IntGauge = register_int_gauge!(opts!(
+        "registration_deadline_blocks",
+        "The blocks until the registration deadline of next epoch.",
+    ))
+    .unwrap();
+    pub static ref REGISTRATION_FAILURES: IntGauge = register_int_gauge!(opts!(
+        "registration_failures",
+        "The consecutive failures of epoch registration.",
+    ))
+    .unwrap();
+    pub static ref SIGNER_BALANCE: Gauge = register_gauge!(opts!(
+        "signer_balance",
+        "The balance of the signer account in ether.",
+    ))
+    .unwrap();
+    pub static ref MINER_BALANCE: Gauge = register_gauge!(opts!(
+        "miner_balance",
+        "The balance of the miner account in ether.",
+    ))
+    .unwrap();
+    pub static ref SIGNER_REGISTRATIONS_REMAINING: IntGauge = register_int_gauge!(opts!(
+        "signer_registrations_remaining",
+        "The estimated epoch registrations the signer balance covers.",
+    ))
+    .unwrap();
+    pub static ref MINER_SUBMISSIONS_REMAINING: IntGauge = register_int_gauge!(opts!(
+        "miner_submissions_remaining",
+        "The estimated sample submissions the miner balance covers.",
+    ))
+    .unwrap();
+    pub static ref BLS_KEY_MISMATCH: IntGauge = register_int_gauge!(opts!(
+        "bls_key_mismatch",
+        "1 if the local BLS key differs from the one registered on chain.",
+    ))
+    .unwrap();
+    pub static ref SCRUBBED_SLICES: IntCounter = register_int_counter!(opts!(
+        "scrubbed_slices_total",
+        "Number of stored slices read back by the scrubber."
+    ))
+    .unwrap();
+    pub static ref CORRUPTED_SLICES: IntCounter = register_int_counter!(opts!(
+        "corrupted_slices_total",
+        "Number of stored slices the scrubber found corrupted."
+    ))
+    .unwrap();
+    pub static ref DB_SIZE_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        opts!(
+            "db_size_bytes",
+            "The size on disk of the main database and of each slice partition, named by epochs."
+        ),
+        &["database"]
+    )
+    .unwrap();
+    pub static ref DB_COLUMN_KEYS: IntGaugeVec = register_int_gauge_vec!(
+        opts!(
+            "db_column_keys",
+            "The estimated number of keys in each database column."
+        ),
+        &["database", "column"]
+    )
+    .unwrap();
+    pub static ref DB_COLUMN_SIZE_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        opts!(
+            "db_column_size_bytes",
+            "The size of the SST files of each database column."
+        ),
+        &["database", "column"]
+    )
+    .unwrap();
+    pub static ref DB_BLOCK_CACHE_HIT_RATIO: GaugeVec = register_gauge_vec!(
+        opts!(
+            "db_block_cache_hit_ratio",
+            "The block cache hit ratio of each database since it was opened."
+        ),
+        &["database"]
+    )
+    .unwrap();
+    pub static ref DB_WRITE_STALL_SECONDS: GaugeVec = register_gauge_vec!(
+        opts!(
+            "db_write_stall_seconds",
+            "The time writes to each database were stalled since it was opened."
+        ),
+        &["database"]
+    )
+    .unwrap();
+    pub static ref DB_PENDING_COMPACTION_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        opts!(
+            "db_pending_compaction_bytes",
+            "The estimated bytes compactions of each database have to rewrite."
+        ),
+        &["database"]
+    )
+    .unwrap();
+    pub static ref EPOCH_BLOBS: IntGaugeVec = register_int_gauge_vec!(
+        opts!("epoch_blobs", "The blobs with slices stored in each epoch."),
+        &["epoch"]
+    )
+    .unwrap();
+    pub static ref EPOCH_SLICES: IntGaugeVec = register_int_gauge_vec!(
+        opts!("epoch_slices", "The slices stored in each epoch."),
+        &["epoch"]
+    )
+    .unwrap();
+    pub static ref EPOCH_BYTES: IntGaugeVec = register_int_gauge_vec!(
+        opts!("epoch_bytes", "The bytes of slices stored in each epoch."),
+        &["epoch"]
+    )
+    .unwrap();
+    pub static ref STORED_SLICES: IntGauge =
+        register_int_gauge!(opts!("stored_slices", "The slices stored in all epochs.",)).unwrap();
     pub static ref MINER_HISTOGRAM: HistogramVec = register_histogram_vec!(
         "miner_duration_seconds",
         "The miner duration for each stage in seconds.",